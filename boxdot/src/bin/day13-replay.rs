@@ -0,0 +1,38 @@
+use advent_of_code_2019::day09::parse;
+use advent_of_code_2019::day13::replay::{self, Recording};
+use advent_of_code_2019::day13::{Arcade, FollowBall};
+
+const USAGE: &str = "Usage:
+    day13-replay record <day13.txt> <recording>
+    day13-replay play <recording> [speed]
+    day13-replay verify <day13.txt> <recording>";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["record", input_file, recording_file] => {
+            let mem = parse(&std::fs::read_to_string(input_file)?);
+            let mut arcade = Arcade::with_quarters(mem, 2).record();
+            let outcome = arcade.run(&mut FollowBall)?;
+            arcade
+                .into_recording()
+                .ok_or("recording missing")?
+                .save(recording_file)?;
+            println!("{:?}", outcome);
+        }
+        ["play", recording_file] => replay::play(&Recording::load(recording_file)?, 1.0)?,
+        ["play", recording_file, speed] => {
+            replay::play(&Recording::load(recording_file)?, speed.parse()?)?
+        }
+        ["verify", input_file, recording_file] => {
+            let mem = parse(&std::fs::read_to_string(input_file)?);
+            let recording = Recording::load(recording_file)?;
+            let outcome = replay::verify(Arcade::with_quarters(mem, 2), &recording)?;
+            println!("{:?}", outcome);
+        }
+        _ => return Err(USAGE.into()),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use advent_of_code_2019::day09::parse;
+use advent_of_code_2019::day15::{self, Droid, Flood};
+use std::time::Duration;
+
+const USAGE: &str = "Usage:
+    day15-oxygen <day15.txt> [delay-ms]";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (input_file, delay_ms) = match args.as_slice() {
+        [input_file] => (input_file, 50),
+        [input_file, delay_ms] => (input_file, delay_ms.parse()?),
+        _ => return Err(USAGE.into()),
+    };
+
+    let mem = parse(&std::fs::read_to_string(input_file)?);
+    let map = day15::explore(&mut Droid::new(mem, day15::MAX_VM_STEPS))?;
+    let mut flood = Flood::from_oxygen_systems(map);
+    let minutes = day15::watch(&mut flood, Duration::from_millis(delay_ms))?;
+    println!("Minutes: {}", minutes);
+
+    Ok(())
+}
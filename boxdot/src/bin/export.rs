@@ -0,0 +1,50 @@
+use advent_of_code_2019::image::{self, Image, BLACK, WHITE};
+use advent_of_code_2019::{day08, day09, day11, day19, day24};
+
+const USAGE: &str = "Usage: export <dayYY.txt> <output.{ppm,png,svg}> [scale]
+
+Supported days: 8, 11, 19 and 24 (animated, png only)";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let input_file = args.next().ok_or(USAGE)?;
+    let output_file = args.next().ok_or(USAGE)?;
+    let scale = match args.next() {
+        Some(scale) => scale.parse()?,
+        None => 10,
+    };
+    if let Some(arg) = args.next() {
+        return Err(format!("unexpected argument: {}", arg).into());
+    }
+
+    let re = regex::Regex::new(r"^.*day(\d+).*\.txt$")?;
+    let day: u8 = re
+        .captures(&input_file)
+        .and_then(|c| c.get(1).map(|g| g.as_str()))
+        .ok_or_else(|| format!("can't deduce day from: {}", input_file))?
+        .parse()?;
+
+    let input = std::fs::read_to_string(input_file)?;
+    let white_if = |on: bool| if on { WHITE } else { BLACK };
+    let img = match day {
+        8 => {
+            let img = day08::SpaceImage::decode(&input, day08::WIDTH, day08::HEIGHT)?;
+            Image::from_dense(&img.render(), img.width(), |&c| white_if(c == day08::WHITE))?
+        }
+        11 => Image::from_canvas(&day11::registration(&input)?, |&c| white_if(c == 1), BLACK),
+        19 => {
+            let beam = day19::scan_area(&day09::parse(&input), 50, 50)?;
+            Image::from_canvas(&beam, |&pulled| white_if(pulled), BLACK)
+        }
+        24 => {
+            let frames: Result<Vec<_>, _> = day24::generations(&input)
+                .iter()
+                .map(|bugs| Image::from_dense(bugs, 5, |&bug| white_if(bug)))
+                .collect();
+            let file = std::io::BufWriter::new(std::fs::File::create(output_file)?);
+            return image::write_animation(&frames?, file, scale, 500);
+        }
+        _ => return Err(format!("no image for day: {}", day).into()),
+    };
+    img.save(output_file, scale)
+}
@@ -3,8 +3,7 @@ pub fn solve(input: &str) -> (u64, u64) {
 
     let part1 = masses.clone().filter_map(fuel).sum();
     let part2 = masses
-        .map(|mass| std::iter::successors(fuel(mass), |m| fuel(*m)))
-        .flatten()
+        .flat_map(|mass| std::iter::successors(fuel(mass), |m| fuel(*m)))
         .sum();
 
     (part1, part2)
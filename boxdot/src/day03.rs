@@ -1,17 +1,15 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 pub fn solve(input: &str) -> Option<(usize, usize)> {
-    let mut lines = input.lines();
-    let wire1 = parse(lines.next()?);
-    let wire2 = parse(lines.next()?);
-
-    let grid = build_grid(&wire1, &wire2);
-    Some((part1(&grid)?, part2(&grid)?))
+    let wires: Vec<Wire> = input.lines().map(Wire::parse).collect::<Option<_>>()?;
+    let crossings = intersections(&wires);
+    Some((closest(&crossings)?, shortest_delay(&crossings)?))
 }
 
-fn parse(s: &str) -> Vec<(Direction, usize)> {
-    s.split(',')
-        .filter_map(|s| {
+fn parse(s: &str) -> Option<Vec<(Direction, usize)>> {
+    s.trim()
+        .split(',')
+        .map(|s| {
             let direction = Direction::from_char(s.chars().next()?)?;
             let steps = s[1..].parse().ok()?;
             Some((direction, steps))
@@ -19,6 +17,7 @@ fn parse(s: &str) -> Vec<(Direction, usize)> {
         .collect()
 }
 
+#[derive(Debug, Clone, Copy)]
 enum Direction {
     Left,
     Right,
@@ -38,60 +37,288 @@ impl Direction {
     }
 }
 
-fn follow_wire(wire: &[(Direction, usize)], mut action: impl FnMut(isize, isize, usize)) {
-    let mut step = 0;
-    let mut pos_x = 0;
-    let mut pos_y = 0;
-    for (direction, steps) in wire {
-        for _ in 0..*steps {
-            action(pos_x, pos_y, step);
-            match direction {
-                Direction::Up => pos_y += 1,
-                Direction::Down => pos_y -= 1,
-                Direction::Left => pos_x -= 1,
-                Direction::Right => pos_x += 1,
-            }
-            step += 1;
-        }
+pub type Point = (i64, i64);
+
+const ORIGIN: Point = (0, 0);
+
+fn manhattan((x0, y0): Point, (x1, y1): Point) -> usize {
+    ((x0 - x1).abs() + (y0 - y1).abs()) as usize
+}
+
+/// Axis-aligned piece of a wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub start: Point,
+    pub end: Point,
+    /// Steps the wire took before reaching `start`.
+    pub steps: usize,
+}
+
+impl Segment {
+    fn is_horizontal(&self) -> bool {
+        self.start.1 == self.end.1
+    }
+
+    /// Returns the (min, max) corners.
+    fn bounds(&self) -> (Point, Point) {
+        let min = (self.start.0.min(self.end.0), self.start.1.min(self.end.1));
+        let max = (self.start.0.max(self.end.0), self.start.1.max(self.end.1));
+        (min, max)
+    }
+
+    /// Steps the wire took to reach `pos` on this segment.
+    pub fn steps_at(&self, pos: Point) -> usize {
+        self.steps + manhattan(self.start, pos)
     }
 }
 
-type Grid = HashMap<(isize, isize), (Option<usize>, Option<usize>)>;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wire {
+    segments: Vec<Segment>,
+}
 
-fn build_grid(wire1: &[(Direction, usize)], wire2: &[(Direction, usize)]) -> Grid {
-    let mut grid = HashMap::new();
-    follow_wire(&wire1, |x, y, step| {
-        grid.entry((x, y))
-            .and_modify(|(steps1, _): &mut (Option<usize>, Option<usize>)| {
-                steps1.get_or_insert(step);
+impl Wire {
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut pos = ORIGIN;
+        let mut steps = 0;
+        let segments = parse(s)?
+            .into_iter()
+            .filter(|&(_, len)| len != 0)
+            .map(|(direction, len)| {
+                let d = len as i64;
+                let end = match direction {
+                    Direction::Up => (pos.0, pos.1 + d),
+                    Direction::Down => (pos.0, pos.1 - d),
+                    Direction::Left => (pos.0 - d, pos.1),
+                    Direction::Right => (pos.0 + d, pos.1),
+                };
+                let segment = Segment {
+                    start: pos,
+                    end,
+                    steps,
+                };
+                pos = end;
+                steps += len;
+                segment
             })
-            .or_insert((Some(step), None));
-    });
-    follow_wire(&wire2, |x, y, step| {
-        grid.entry((x, y))
-            .and_modify(|(_, steps2)| {
-                steps2.get_or_insert(step);
-            })
-            .or_insert((None, Some(step)));
+            .collect();
+        Some(Self { segments })
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+}
+
+/// Points shared by two segments: a single crossing or a collinear overlap from `from` to `to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Intersection {
+    /// Wire and segment index of both segments.
+    pub ids: ((usize, usize), (usize, usize)),
+    pub segments: (Segment, Segment),
+    pub from: Point,
+    pub to: Point,
+}
+
+impl Intersection {
+    /// Number of shared points, which is at least one.
+    pub fn num_points(&self) -> usize {
+        manhattan(self.from, self.to) + 1
+    }
+
+    /// Combined number of steps both wires take to reach `pos`.
+    pub fn delay_at(&self, pos: Point) -> usize {
+        self.segments.0.steps_at(pos) + self.segments.1.steps_at(pos)
+    }
+
+    // Both metrics are linear along the shared points apart from a kink next to the origin, so
+    // it is sufficient to look at the ends and the points around the origin's projection.
+    fn candidates(&self) -> impl Iterator<Item = Point> {
+        let ((x0, y0), (x1, y1)) = (
+            (self.from.0.min(self.to.0), self.from.1.min(self.to.1)),
+            (self.from.0.max(self.to.0), self.from.1.max(self.to.1)),
+        );
+        let clamp = |x: i64, min, max| x.max(min).min(max);
+        let projection = (clamp(0, x0, x1), clamp(0, y0, y1));
+        let neighbors = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .iter()
+            .map(move |(dx, dy)| {
+                (
+                    clamp(projection.0 + dx, x0, x1),
+                    clamp(projection.1 + dy, y0, y1),
+                )
+            });
+        vec![self.from, self.to, projection]
+            .into_iter()
+            .chain(neighbors)
+            .filter(|&pos| pos != ORIGIN)
+    }
+
+    /// Shared point closest to the origin (which itself does not count) and its distance.
+    pub fn closest(&self) -> Option<(Point, usize)> {
+        self.candidates()
+            .map(|pos| (pos, manhattan(ORIGIN, pos)))
+            .min_by_key(|&(_, dist)| dist)
+    }
+
+    /// Shared point with the smallest combined signal delay (apart from the origin).
+    pub fn shortest_delay(&self) -> Option<(Point, usize)> {
+        self.candidates()
+            .map(|pos| (pos, self.delay_at(pos)))
+            .min_by_key(|&(_, delay)| delay)
+    }
+}
+
+type Id = (usize, usize);
+
+/// Extent of a segment along its line.
+type Span<'a> = (i64, i64, Id, &'a Segment);
+
+fn intersect((id_a, a): (Id, &Segment), (id_b, b): (Id, &Segment)) -> Option<Intersection> {
+    let ((ax0, ay0), (ax1, ay1)) = a.bounds();
+    let ((bx0, by0), (bx1, by1)) = b.bounds();
+    let from = (ax0.max(bx0), ay0.max(by0));
+    let to = (ax1.min(bx1), ay1.min(by1));
+    if from.0 > to.0 || from.1 > to.1 {
+        return None;
+    }
+    Some(Intersection {
+        ids: (id_a, id_b),
+        segments: (*a, *b),
+        from,
+        to,
+    })
+}
+
+/// Finds all pairs of intersecting segments with a sweep-line along the x-axis.
+///
+/// Perpendicular pairs are found by keeping the horizontal segments under the sweep-line in a
+/// tree ordered by y, collinear overlaps by sweeping over the segments of each line.
+fn sweep<'a>(segments: impl Iterator<Item = (Id, &'a Segment)> + Clone) -> Vec<Intersection> {
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+    enum Event {
+        Insert,
+        Query,
+        Remove,
+    }
+
+    let mut crossings = Vec::new();
+
+    let mut events: Vec<(i64, Event, Id, &Segment)> = Vec::new();
+    for (id, segment) in segments.clone() {
+        let ((x0, _), (x1, _)) = segment.bounds();
+        if segment.is_horizontal() {
+            events.push((x0, Event::Insert, id, segment));
+            events.push((x1, Event::Remove, id, segment));
+        } else {
+            events.push((x0, Event::Query, id, segment));
+        }
+    }
+    events.sort_by_key(|&(x, kind, id, _)| (x, kind, id));
+
+    let mut active: BTreeMap<i64, Vec<(Id, &Segment)>> = BTreeMap::new();
+    for (_, kind, id, segment) in events {
+        let ((_, y0), (_, y1)) = segment.bounds();
+        match kind {
+            Event::Insert => active.entry(y0).or_default().push((id, segment)),
+            Event::Query => {
+                for (_, horizontals) in active.range(y0..=y1) {
+                    crossings.extend(
+                        horizontals
+                            .iter()
+                            .filter_map(|&h| intersect(h, (id, segment))),
+                    );
+                }
+            }
+            Event::Remove => {
+                if let Some(horizontals) = active.get_mut(&y0) {
+                    horizontals.retain(|&(other, _)| other != id);
+                }
+            }
+        }
+    }
+
+    // collinear overlaps: group segments by their line and sweep along it
+    let mut lines: BTreeMap<(bool, i64), Vec<Span>> = BTreeMap::new();
+    for (id, segment) in segments {
+        let ((x0, y0), (x1, y1)) = segment.bounds();
+        if segment.is_horizontal() {
+            lines
+                .entry((true, y0))
+                .or_default()
+                .push((x0, x1, id, segment));
+        } else {
+            lines
+                .entry((false, x0))
+                .or_default()
+                .push((y0, y1, id, segment));
+        }
+    }
+    for (_, mut line) in lines {
+        line.sort_by_key(|&(start, _, id, _)| (start, id));
+        let mut open: Vec<(i64, Id, &Segment)> = Vec::new();
+        for (start, end, id, segment) in line {
+            open.retain(|&(other_end, _, _)| other_end >= start);
+            crossings.extend(
+                open.iter().filter_map(|&(_, other_id, other)| {
+                    intersect((other_id, other), (id, segment))
+                }),
+            );
+            open.push((end, id, segment));
+        }
+    }
+
+    crossings
+}
+
+/// All intersections between segments of different wires.
+pub fn intersections(wires: &[Wire]) -> Vec<Intersection> {
+    let segments = wires.iter().enumerate().flat_map(|(i, wire)| {
+        wire.segments
+            .iter()
+            .enumerate()
+            .map(move |(j, segment)| ((i, j), segment))
     });
-    grid
+    sweep(segments)
+        .into_iter()
+        .filter(|c| (c.ids.0).0 != (c.ids.1).0)
+        .collect()
 }
 
-fn part1(grid: &Grid) -> Option<usize> {
-    grid.iter()
-        .filter(|&((x, y), (wire1, wire2))| {
-            wire1.is_some() && wire2.is_some() && (*x != 0 || *y != 0)
+/// All places where the wire crosses or runs along itself.
+///
+/// Consecutive segments always share their joint, which is only reported if the wire turns back
+/// onto itself.
+pub fn self_intersections(wire: &Wire) -> Vec<Intersection> {
+    let segments = wire
+        .segments
+        .iter()
+        .enumerate()
+        .map(|(j, segment)| ((0, j), segment));
+    sweep(segments)
+        .into_iter()
+        .filter(|c| {
+            let (a, b) = ((c.ids.0).1, (c.ids.1).1);
+            a.max(b) - a.min(b) != 1 || c.num_points() > 1
         })
-        .map(|((x, y), _)| (x.abs() + y.abs()) as usize)
+        .collect()
+}
+
+/// Manhattan distance from the origin to the closest intersection.
+pub fn closest(crossings: &[Intersection]) -> Option<usize> {
+    crossings
+        .iter()
+        .filter_map(Intersection::closest)
+        .map(|(_, dist)| dist)
         .min()
 }
 
-fn part2(grid: &Grid) -> Option<usize> {
-    grid.iter()
-        .filter(|&((x, y), (wire1, wire2))| {
-            wire1.is_some() && wire2.is_some() && (*x != 0 || *y != 0)
-        })
-        .map(|(_, (steps1, steps2))| steps1.unwrap() + steps2.unwrap())
+/// Fewest combined steps the wires take to reach an intersection.
+pub fn shortest_delay(crossings: &[Intersection]) -> Option<usize> {
+    crossings
+        .iter()
+        .filter_map(Intersection::shortest_delay)
+        .map(|(_, delay)| delay)
         .min()
 }
 
@@ -99,33 +326,82 @@ fn part2(grid: &Grid) -> Option<usize> {
 mod tests {
     use super::*;
 
+    fn wires(a: &str, b: &str) -> Vec<Intersection> {
+        intersections(&[Wire::parse(a).unwrap(), Wire::parse(b).unwrap()])
+    }
+
     #[test]
     fn test_part1() {
-        let wire1 = parse("R8,U5,L5,D3");
-        let wire2 = parse("U7,R6,D4,L4");
-        assert_eq!(part1(&build_grid(&wire1, &wire2)), Some(6));
+        let crossings = wires("R8,U5,L5,D3", "U7,R6,D4,L4");
+        assert_eq!(closest(&crossings), Some(6));
 
-        let wire1 = parse("R75,D30,R83,U83,L12,D49,R71,U7,L72");
-        let wire2 = parse("U62,R66,U55,R34,D71,R55,D58,R83");
-        assert_eq!(part1(&build_grid(&wire1, &wire2)), Some(159));
+        let crossings = wires(
+            "R75,D30,R83,U83,L12,D49,R71,U7,L72",
+            "U62,R66,U55,R34,D71,R55,D58,R83",
+        );
+        assert_eq!(closest(&crossings), Some(159));
 
-        let wire1 = parse("R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51");
-        let wire2 = parse("U98,R91,D20,R16,D67,R40,U7,R15,U6,R7");
-        assert_eq!(part1(&build_grid(&wire1, &wire2)), Some(135));
+        let crossings = wires(
+            "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51",
+            "U98,R91,D20,R16,D67,R40,U7,R15,U6,R7",
+        );
+        assert_eq!(closest(&crossings), Some(135));
     }
 
     #[test]
     fn test_part2() {
-        let wire1 = parse("R8,U5,L5,D3");
-        let wire2 = parse("U7,R6,D4,L4");
-        assert_eq!(part2(&build_grid(&wire1, &wire2)), Some(30));
+        let crossings = wires("R8,U5,L5,D3", "U7,R6,D4,L4");
+        assert_eq!(shortest_delay(&crossings), Some(30));
 
-        let wire1 = parse("R75,D30,R83,U83,L12,D49,R71,U7,L72");
-        let wire2 = parse("U62,R66,U55,R34,D71,R55,D58,R83");
-        assert_eq!(part2(&build_grid(&wire1, &wire2)), Some(610));
+        let crossings = wires(
+            "R75,D30,R83,U83,L12,D49,R71,U7,L72",
+            "U62,R66,U55,R34,D71,R55,D58,R83",
+        );
+        assert_eq!(shortest_delay(&crossings), Some(610));
+
+        let crossings = wires(
+            "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51",
+            "U98,R91,D20,R16,D67,R40,U7,R15,U6,R7",
+        );
+        assert_eq!(shortest_delay(&crossings), Some(410));
+    }
+
+    #[test]
+    fn test_overlaps_and_many_wires() {
+        // the wires run along each other from (2, 0) to (5, 0)
+        let crossings = wires("R5", "U1,R2,D1,R3,U4");
+        assert_eq!(crossings.len(), 4);
+        assert!(crossings.iter().any(|c| (c.from, c.to) == ((2, 0), (5, 0))));
+        assert_eq!(closest(&crossings), Some(2));
+        assert_eq!(shortest_delay(&crossings), Some(2 + 4));
+
+        // running along the other wire through the origin: only the origin itself does not count
+        let crossings = wires("R5", "L2,R4");
+        assert_eq!(closest(&crossings), Some(1));
+        assert_eq!(shortest_delay(&crossings), Some(1 + 5));
+
+        let many: Vec<Wire> = ["R10", "U3,R5,D6", "D3,R7,U6"]
+            .iter()
+            .map(|s| Wire::parse(s).unwrap())
+            .collect();
+        let crossings = intersections(&many);
+        assert_eq!(crossings.len(), 6);
+        assert_eq!(closest(&crossings), Some(5));
+    }
+
+    #[test]
+    fn test_self_intersections() {
+        let wire = Wire::parse("R4,U2,L2,D4").unwrap();
+        let crossings = self_intersections(&wire);
+        assert_eq!(crossings.len(), 1);
+        assert_eq!(crossings[0].from, (2, 0));
+        assert_eq!(crossings[0].num_points(), 1);
+        assert_eq!(crossings[0].delay_at((2, 0)), 2 + 10);
 
-        let wire1 = parse("R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51");
-        let wire2 = parse("U98,R91,D20,R16,D67,R40,U7,R15,U6,R7");
-        assert_eq!(part2(&build_grid(&wire1, &wire2)), Some(410));
+        // turning back runs along the previous segment
+        let wire = Wire::parse("R4,L2").unwrap();
+        let crossings = self_intersections(&wire);
+        assert_eq!((crossings[0].from, crossings[0].to), ((2, 0), (4, 0)));
+        assert_eq!(crossings[0].num_points(), 3);
     }
 }
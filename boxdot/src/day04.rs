@@ -1,57 +1,314 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ops::RangeInclusive;
 
-pub fn solve(input: &str) -> Option<(usize, usize)> {
+pub fn solve(input: &str) -> Option<(u128, u128)> {
     let range = parse(input)?;
     Some((part1(range.clone()), part2(range)))
 }
 
-fn parse(input: &str) -> Option<RangeInclusive<usize>> {
-    let mut parts = input.split('-');
+fn parse(input: &str) -> Option<RangeInclusive<u128>> {
+    let mut parts = input.trim().split('-');
     let start = parts.next()?.parse().ok()?;
-    let end: usize = parts.next()?.parse().ok()?;
+    let end: u128 = parts.next()?.parse().ok()?;
     Some(start..=end)
 }
 
-type Password = [u8; 6];
+fn part1(range: RangeInclusive<u128>) -> u128 {
+    Counter::new(vec![Rule::NonDecreasing, Rule::has_pair()]).count(range)
+}
+
+fn part2(range: RangeInclusive<u128>) -> u128 {
+    Counter::new(vec![Rule::NonDecreasing, Rule::has_exact_pair()]).count(range)
+}
+
+/// Constraint on the decimal digits of a number (without leading zeros).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rule {
+    /// Going from left to right, the digits never decrease.
+    NonDecreasing,
+    /// Some run of equal adjacent digits has a length in `min..=max`.
+    Run { min: usize, max: Option<usize> },
+    /// No run of equal adjacent digits is longer than this.
+    MaxRun(usize),
+    /// None of these digits appears.
+    Banned(Vec<u8>),
+}
+
+impl Rule {
+    /// Two adjacent digits are the same.
+    pub fn has_pair() -> Self {
+        Rule::Run { min: 2, max: None }
+    }
+
+    /// Two adjacent digits are the same, but not part of a larger group.
+    pub fn has_exact_pair() -> Self {
+        Rule::Run {
+            min: 2,
+            max: Some(2),
+        }
+    }
+}
+
+/// Digits processed so far, reduced to what the rules can still distinguish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct State {
+    last: Option<u8>,
+    /// Length of the current run, saturating at the counter's cap.
+    run: usize,
+    /// Bit i is set if the i-th `Run` rule is satisfied by a finished run.
+    found: u64,
+}
+
+const START: State = State {
+    last: None,
+    run: 0,
+    found: 0,
+};
+
+/// Counts and enumerates the numbers satisfying all rules with a digit DP.
+///
+/// Numbers are built digit by digit between the bounds of each length; once a prefix lies strictly
+/// inside the bounds, the number of completions only depends on the remaining length and the
+/// state, which is memoized.
+#[derive(Debug)]
+pub struct Counter {
+    rules: Vec<Rule>,
+    banned: [bool; 10],
+    run_cap: usize,
+    required: u64,
+    memo: RefCell<HashMap<(usize, State), u128>>,
+}
+
+impl Counter {
+    /// Panics if there are more than 64 `Run` rules.
+    pub fn new(rules: Vec<Rule>) -> Self {
+        let mut banned = [false; 10];
+        let mut run_cap = 1;
+        let mut required = 0;
+        let mut num_runs = 0;
+        for rule in &rules {
+            match rule {
+                Rule::NonDecreasing => {}
+                Rule::Run { min, max } => {
+                    assert!(num_runs < 64, "too many run rules");
+                    required |= 1 << num_runs;
+                    num_runs += 1;
+                    run_cap = run_cap.max(*min).max(max.unwrap_or(0));
+                }
+                Rule::MaxRun(max) => run_cap = run_cap.max(*max),
+                Rule::Banned(digits) => {
+                    for &d in digits.iter().filter(|&&d| d < 10) {
+                        banned[d as usize] = true;
+                    }
+                }
+            }
+        }
+        Self {
+            rules,
+            banned,
+            // one more than any threshold, so that capped lengths compare like real ones
+            run_cap: run_cap + 1,
+            required,
+            memo: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Marks the `Run` rules satisfied by a finished run.
+    fn close_run(&self, mut state: State) -> State {
+        let runs = self.rules.iter().filter_map(|rule| match rule {
+            Rule::Run { min, max } => Some((min, max)),
+            _ => None,
+        });
+        for (index, (min, max)) in runs.enumerate() {
+            if *min <= state.run && max.is_none_or(|max| state.run <= max) {
+                state.found |= 1 << index;
+            }
+        }
+        state
+    }
+
+    /// Appends a digit, or returns `None` if no number with this prefix can match.
+    fn push(&self, state: State, digit: u8) -> Option<State> {
+        if self.banned[digit as usize] {
+            return None;
+        }
+        let mut state = match state.last {
+            Some(last) if last == digit => State {
+                run: (state.run + 1).min(self.run_cap),
+                ..state
+            },
+            Some(last) => {
+                if last > digit && self.rules.contains(&Rule::NonDecreasing) {
+                    return None;
+                }
+                self.close_run(state)
+            }
+            None => state,
+        };
+        if state.last != Some(digit) {
+            state.last = Some(digit);
+            state.run = 1;
+        }
+        let too_long = self
+            .rules
+            .iter()
+            .any(|rule| matches!(rule, Rule::MaxRun(max) if state.run > *max));
+        if too_long {
+            None
+        } else {
+            Some(state)
+        }
+    }
+
+    fn accept(&self, state: State) -> bool {
+        state.last.is_some() && self.close_run(state).found & self.required == self.required
+    }
+
+    /// Number of matching completions with `left` unconstrained digits.
+    fn free(&self, left: usize, state: State) -> u128 {
+        if left == 0 {
+            return self.accept(state) as u128;
+        }
+        if let Some(&count) = self.memo.borrow().get(&(left, state)) {
+            return count;
+        }
+        let count = (0..10)
+            .filter_map(|d| self.push(state, d))
+            .map(|next| self.free(left - 1, next))
+            .sum();
+        self.memo.borrow_mut().insert((left, state), count);
+        count
+    }
+
+    fn count_between(&self, node: Node, lower: &[u8], upper: &[u8]) -> u128 {
+        if node.pos == upper.len() {
+            return self.accept(node.state) as u128;
+        }
+        if !node.tight_lo && !node.tight_hi {
+            return self.free(upper.len() - node.pos, node.state);
+        }
+        node.children(self, lower, upper)
+            .map(|child| self.count_between(child, lower, upper))
+            .sum()
+    }
+
+    /// Number of matches in the range.
+    pub fn count(&self, range: RangeInclusive<u128>) -> u128 {
+        length_bounds(range)
+            .iter()
+            .map(|(lower, upper)| self.count_between(Node::root(), lower, upper))
+            .sum()
+    }
+
+    pub fn matches(&self, n: u128) -> bool {
+        digits(n)
+            .into_iter()
+            .try_fold(START, |state, d| self.push(state, d))
+            .is_some_and(|state| self.accept(state))
+    }
+
+    /// Matches in the range in ascending order; branches without matches are skipped.
+    pub fn enumerate(&self, range: RangeInclusive<u128>) -> impl Iterator<Item = u128> + '_ {
+        let bounds = length_bounds(range);
+        let mut stack: Vec<(usize, Node)> =
+            (0..bounds.len()).rev().map(|i| (i, Node::root())).collect();
+        std::iter::from_fn(move || {
+            while let Some((i, node)) = stack.pop() {
+                let (lower, upper) = &bounds[i];
+                if node.pos == upper.len() {
+                    if self.accept(node.state) {
+                        return Some(node.value);
+                    }
+                    continue;
+                }
+                if !node.tight_lo
+                    && !node.tight_hi
+                    && self.free(upper.len() - node.pos, node.state) == 0
+                {
+                    continue;
+                }
+                let children: Vec<Node> = node.children(self, lower, upper).collect();
+                stack.extend(children.into_iter().rev().map(|child| (i, child)));
+            }
+            None
+        })
+    }
+}
+
+/// Prefix of a number between a lower and an upper bound of the same length.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    pos: usize,
+    state: State,
+    value: u128,
+    tight_lo: bool,
+    tight_hi: bool,
+}
 
-fn part1(range: RangeInclusive<usize>) -> usize {
-    let adjacent_digits_are_the_same = |digits: &Password| digits.windows(2).any(|w| w[0] == w[1]);
-    let never_decrease = |digits: &Password| digits.windows(2).all(|w| w[0] <= w[1]);
+impl Node {
+    fn root() -> Self {
+        Self {
+            pos: 0,
+            state: START,
+            value: 0,
+            tight_lo: true,
+            tight_hi: true,
+        }
+    }
 
-    range
-        .map(digits)
-        .filter(adjacent_digits_are_the_same)
-        .filter(never_decrease)
-        .count()
+    fn children<'a>(
+        self,
+        counter: &'a Counter,
+        lower: &[u8],
+        upper: &[u8],
+    ) -> impl Iterator<Item = Node> + 'a {
+        let lo = if self.tight_lo { lower[self.pos] } else { 0 };
+        let hi = if self.tight_hi { upper[self.pos] } else { 9 };
+        (lo..=hi).filter_map(move |d| {
+            Some(Node {
+                pos: self.pos + 1,
+                state: counter.push(self.state, d)?,
+                value: self.value * 10 + d as u128,
+                tight_lo: self.tight_lo && d == lo,
+                tight_hi: self.tight_hi && d == hi,
+            })
+        })
+    }
 }
 
-fn part2(range: RangeInclusive<usize>) -> usize {
-    let adjacent_digits_are_the_same_prime = |digits: &Password| {
-        let mut extended = [255; 8];
-        extended[1..7].copy_from_slice(digits);
-        extended
-            .windows(4)
-            .any(|x| x[0] != x[1] && x[1] == x[2] && x[2] != x[3])
-    };
-    let never_decrease = |digits: &Password| digits.windows(2).all(|w| w[0] <= w[1]);
-
-    range
-        .map(digits)
-        .filter(adjacent_digits_are_the_same_prime)
-        .filter(never_decrease)
-        .count()
+fn digits(n: u128) -> Vec<u8> {
+    n.to_string().bytes().map(|b| b - b'0').collect()
 }
 
-#[allow(clippy::unreadable_literal, clippy::identity_op)]
-fn digits(n: usize) -> Password {
-    [
-        (n % 1000000 / 100000) as u8,
-        (n % 100000 / 10000) as u8,
-        (n % 10000 / 1000) as u8,
-        (n % 1000 / 100) as u8,
-        (n % 100 / 10) as u8,
-        (n % 10 / 1) as u8,
-    ]
+/// Splits the range into (lower, upper) digit bounds of numbers with the same length.
+fn length_bounds(range: RangeInclusive<u128>) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let (start, end) = range.into_inner();
+    if start > end {
+        return Vec::new();
+    }
+    let (start, end) = (digits(start), digits(end));
+    (start.len()..=end.len())
+        .map(|len| {
+            let lower = if len == start.len() {
+                start.clone()
+            } else {
+                let mut lower = vec![0; len];
+                lower[0] = 1;
+                lower
+            };
+            let upper = if len == end.len() {
+                end.clone()
+            } else {
+                vec![9; len]
+            };
+            (lower, upper)
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -71,4 +328,91 @@ mod tests {
         assert_eq!(part2(123444..=123444), 0);
         assert_eq!(part2(111122..=111122), 1);
     }
+
+    /// Checks the rules on the digits directly, without the automaton of the counter.
+    fn satisfies(rules: &[Rule], n: u128) -> bool {
+        let digits: Vec<u8> = n.to_string().bytes().map(|b| b - b'0').collect();
+        let mut runs: Vec<usize> = Vec::new();
+        for (i, d) in digits.iter().enumerate() {
+            match runs.last_mut() {
+                Some(run) if digits[i - 1] == *d => *run += 1,
+                _ => runs.push(1),
+            }
+        }
+        rules.iter().all(|rule| match rule {
+            Rule::NonDecreasing => digits.windows(2).all(|w| w[0] <= w[1]),
+            Rule::Run { min, max } => runs
+                .iter()
+                .any(|&run| run >= *min && max.is_none_or(|max| run <= max)),
+            Rule::MaxRun(max) => runs.iter().all(|&run| run <= *max),
+            Rule::Banned(banned) => digits.iter().all(|d| !banned.contains(d)),
+        })
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let rule_sets = vec![
+            vec![Rule::NonDecreasing, Rule::has_exact_pair()],
+            vec![Rule::has_pair(), Rule::Banned(vec![3, 7])],
+            vec![Rule::MaxRun(2), Rule::Run { min: 2, max: None }],
+            vec![],
+        ];
+        for rules in rule_sets {
+            let counter = Counter::new(rules.clone());
+            for range in [0..=2000, 95..=1234, 999..=1000, RangeInclusive::new(7, 3)] {
+                let expected: Vec<u128> = range.clone().filter(|&n| satisfies(&rules, n)).collect();
+                assert_eq!(counter.count(range.clone()), expected.len() as u128);
+                assert_eq!(counter.enumerate(range).collect::<Vec<_>>(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_puzzle_rules() {
+        // the rules of the puzzle as stated, on six digits
+        let digits = |n: u128| -> Vec<u8> { n.to_string().bytes().map(|b| b - b'0').collect() };
+        let never_decrease = |d: &[u8]| d.windows(2).all(|w| w[0] <= w[1]);
+        let pair = |d: &[u8]| d.windows(2).any(|w| w[0] == w[1]);
+        let exact_pair = |d: &[u8]| {
+            let mut extended = vec![255];
+            extended.extend(d);
+            extended.push(255);
+            extended
+                .windows(4)
+                .any(|w| w[0] != w[1] && w[1] == w[2] && w[2] != w[3])
+        };
+        let range = 100_000..=399_999;
+        let count = |rule: &dyn Fn(&[u8]) -> bool| {
+            range
+                .clone()
+                .map(digits)
+                .filter(|d| never_decrease(d) && rule(d))
+                .count() as u128
+        };
+        assert_eq!(part1(range.clone()), count(&pair));
+        assert_eq!(part2(range.clone()), count(&exact_pair));
+    }
+
+    #[test]
+    fn test_many_rules() {
+        // only the `Run` rules are limited, wherever they come
+        let mut rules = vec![Rule::MaxRun(3); 70];
+        rules.push(Rule::has_exact_pair());
+        let counter = Counter::new(rules.clone());
+        let expected = (0..=2000).filter(|&n| satisfies(&rules, n)).count() as u128;
+        assert_eq!(counter.count(0..=2000), expected);
+    }
+
+    #[test]
+    fn test_long_ranges() {
+        // non-decreasing numbers with 20 digits only use the digits 1 to 9: C(20 + 8, 8)
+        let counter = Counter::new(vec![Rule::NonDecreasing]);
+        assert_eq!(counter.count(10u128.pow(19)..=10u128.pow(20) - 1), 3108105);
+
+        let counter = Counter::new(vec![Rule::Banned(vec![0, 2, 3, 4, 5, 6, 7, 8, 9])]);
+        assert_eq!(counter.count(0..=u128::MAX), 39);
+        let ones: Vec<u128> = counter.enumerate(0..=10u128.pow(20)).collect();
+        assert_eq!(ones.len(), 20);
+        assert_eq!(ones[19], 11_111_111_111_111_111_111);
+    }
 }
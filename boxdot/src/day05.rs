@@ -96,7 +96,7 @@ pub fn execute(
     mut input: impl FnMut() -> i64,
     mut output: impl FnMut(i64),
 ) -> Result<Option<usize>, Error> {
-    let op = fetch(&mem, ip)?;
+    let op = fetch(mem, ip)?;
     let ip = match op {
         Op::Add(a, b, c) => {
             let value_a = a.value(mem);
@@ -133,11 +133,11 @@ pub fn execute(
             }
         }
         Op::LessThan(a, b, c) => {
-            mem[c.addr()] = if a.value(&mem) < b.value(&mem) { 1 } else { 0 };
+            mem[c.addr()] = if a.value(mem) < b.value(mem) { 1 } else { 0 };
             ip + 4
         }
         Op::Equals(a, b, c) => {
-            mem[c.addr()] = if a.value(&mem) == b.value(&mem) { 1 } else { 0 };
+            mem[c.addr()] = if a.value(mem) == b.value(mem) { 1 } else { 0 };
             ip + 4
         }
         Op::Stop => return Ok(None),
@@ -1,93 +1,236 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::HashMap;
+use std::fmt::Write;
 
 type Error = Box<dyn std::error::Error>;
 
 pub fn solve(input: &str) -> Result<(usize, usize), Error> {
-    let g = parse(input)?;
-    let part1 = g.num_orbits();
-    let part2 = g
-        .shortest_path_len(Vertex("YOU"), Vertex("SAN"))
-        .and_then(|len| len.checked_sub(2))
-        .ok_or_else(|| "path YOU -> SAN not found")?;
+    let tree = OrbitTree::parse(input)?;
+    let part1 = tree.num_orbits();
+    let part2 = tree.transfers("YOU", "SAN").ok_or("YOU or SAN not found")?;
     Ok((part1, part2))
 }
 
-fn parse(input: &str) -> Result<Graph, Error> {
-    let edges: Vec<(Vertex, Vertex)> = input
-        .lines()
-        .filter_map(|s| {
-            let mut parts = s.split(')');
-            let from = parts.next()?;
-            let to = parts.next()?;
-            Some((Vertex(from), Vertex(to)))
-        })
-        .collect();
-    Ok(Graph { edges })
+/// Tree of objects in which every object but the root directly orbits its parent.
+///
+/// Objects are indexed in breadth-first order from the root, and every object stores its
+/// ancestors at distances 1, 2, 4, ... so that ancestor queries take logarithmic time.
+#[derive(Debug, Clone)]
+pub struct OrbitTree<'a> {
+    names: Vec<&'a str>,
+    ids: HashMap<&'a str, usize>,
+    parents: Vec<Option<usize>>,
+    depths: Vec<usize>,
+    /// `jumps[k][v]` is the ancestor of `v` at distance 2^k, or the root if there is none.
+    jumps: Vec<Vec<usize>>,
 }
 
-struct Graph<'a> {
-    edges: Vec<(Vertex<'a>, Vertex<'a>)>,
-}
+impl<'a> OrbitTree<'a> {
+    /// Parses `A)B` lines meaning that B orbits A.
+    pub fn parse(input: &'a str) -> Result<Self, Error> {
+        let edges = input
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(|l| {
+                let mut parts = l.split(')');
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(from), Some(to), None) if !from.is_empty() && !to.is_empty() => {
+                        Ok((from, to))
+                    }
+                    _ => Err(format!("invalid orbit: {}", l)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_edges(&edges)
+    }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-struct Vertex<'a>(&'a str);
+    /// Builds the tree from (center, satellite) pairs.
+    ///
+    /// Fails unless the pairs form a single tree: every object orbits at most one other, exactly
+    /// one object orbits nothing, and there are no cycles.
+    pub fn from_edges(edges: &[(&'a str, &'a str)]) -> Result<Self, Error> {
+        let mut parent_of: HashMap<&str, &str> = HashMap::new();
+        let mut children_of: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut objects: Vec<&str> = Vec::new();
+        for &(from, to) in edges {
+            if let Some(other) = parent_of.insert(to, from) {
+                return Err(format!("{} orbits both {} and {}", to, other, from).into());
+            }
+            children_of.entry(from).or_default().push(to);
+            objects.push(from);
+            objects.push(to);
+        }
 
-impl<'a> Graph<'a> {
-    fn out_edges(&self, v: Vertex<'a>) -> impl Iterator<Item = &Vertex> {
-        self.edges
+        let mut roots: Vec<&str> = objects
             .iter()
-            .filter_map(move |(from, to)| if *from == v { Some(to) } else { None })
-    }
+            .copied()
+            .filter(|o| !parent_of.contains_key(o))
+            .collect();
+        roots.sort_unstable();
+        roots.dedup();
+        let root = match roots[..] {
+            [root] => root,
+            [] if objects.is_empty() => return Err("no orbits".into()),
+            [] => return Err("no root: all objects orbit in cycles".into()),
+            _ => return Err(format!("multiple roots: {}", roots.join(", ")).into()),
+        };
 
-    fn edges(&self, v: Vertex<'a>) -> impl Iterator<Item = &Vertex> {
-        self.edges.iter().filter_map(move |(from, to)| {
-            if *from == v {
-                Some(to)
-            } else if *to == v {
-                Some(from)
-            } else {
-                None
+        let mut names = vec![root];
+        let mut ids: HashMap<&str, usize> = HashMap::new();
+        ids.insert(root, 0);
+        let mut parents = vec![None];
+        let mut depths = vec![0];
+        let mut next = 0;
+        while next < names.len() {
+            let id = next;
+            next += 1;
+            for &child in children_of.get(names[id]).into_iter().flatten() {
+                ids.insert(child, names.len());
+                names.push(child);
+                parents.push(Some(id));
+                depths.push(depths[id] + 1);
             }
+        }
+        if names.len() != parent_of.len() + 1 {
+            let mut in_cycle: Vec<&str> = parent_of
+                .keys()
+                .copied()
+                .filter(|o| !ids.contains_key(o))
+                .collect();
+            in_cycle.sort_unstable();
+            return Err(format!("cycle among {}", in_cycle.join(", ")).into());
+        }
+
+        let max_depth = depths.iter().copied().max().unwrap_or(0);
+        let mut jumps = vec![parents.iter().map(|p| p.unwrap_or(0)).collect::<Vec<_>>()];
+        while 1 << jumps.len() <= max_depth {
+            let prev = jumps.last().unwrap();
+            let level = prev.iter().map(|&v| prev[v]).collect();
+            jumps.push(level);
+        }
+
+        Ok(Self {
+            names,
+            ids,
+            parents,
+            depths,
+            jumps,
         })
     }
 
-    fn num_orbits(&self) -> usize {
-        let mut stack = vec![(Vertex("COM"), 0)];
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
 
-        let mut num_orbits = 0;
-        while let Some((v, depth)) = stack.pop() {
-            num_orbits += depth;
-            for &w in self.out_edges(v) {
-                // we assume that the graph does not contain cycles
-                stack.push((w, depth + 1))
-            }
-        }
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
 
-        num_orbits
+    pub fn root(&self) -> &'a str {
+        self.names[0]
     }
 
-    fn shortest_path_len(&self, orig: Vertex, dest: Vertex) -> Option<usize> {
-        let mut queue = VecDeque::new();
-        queue.push_back((orig, 0));
+    pub fn contains(&self, name: &str) -> bool {
+        self.ids.contains_key(name)
+    }
 
-        // the graph is not directed
-        let mut seen = HashSet::new();
-        seen.insert(orig);
+    pub fn parent(&self, name: &str) -> Option<&'a str> {
+        let id = self.parents[*self.ids.get(name)?]?;
+        Some(self.names[id])
+    }
+
+    /// Number of direct and indirect orbits of the object.
+    pub fn depth(&self, name: &str) -> Option<usize> {
+        Some(self.depths[*self.ids.get(name)?])
+    }
+
+    /// Total number of direct and indirect orbits.
+    pub fn num_orbits(&self) -> usize {
+        self.depths.iter().sum()
+    }
 
-        while let Some((v, depth)) = queue.pop_front() {
-            if v == dest {
-                return Some(depth);
+    fn ancestor_id(&self, mut id: usize, k: usize) -> Option<usize> {
+        if k > self.depths[id] {
+            return None;
+        }
+        for (level, jumps) in self.jumps.iter().enumerate() {
+            if k & (1 << level) != 0 {
+                id = jumps[id];
             }
+        }
+        Some(id)
+    }
 
-            for &w in self.edges(v) {
-                if w != v && !seen.contains(&w) {
-                    seen.insert(w);
-                    queue.push_back((w, depth + 1));
-                }
+    /// Object `k` levels above the given one; the object itself for `k = 0`.
+    pub fn ancestor(&self, name: &str, k: usize) -> Option<&'a str> {
+        let id = self.ancestor_id(*self.ids.get(name)?, k)?;
+        Some(self.names[id])
+    }
+
+    /// Whether `a` is `b` or one of the objects `b` orbits indirectly.
+    pub fn is_ancestor(&self, a: &str, b: &str) -> Option<bool> {
+        let (depth_a, depth_b) = (self.depth(a)?, self.depth(b)?);
+        Some(
+            depth_a <= depth_b
+                && self.ancestor(b, depth_b - depth_a) == Some(self.names[self.ids[a]]),
+        )
+    }
+
+    fn lca_id(&self, mut a: usize, mut b: usize) -> usize {
+        if self.depths[a] < self.depths[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        a = self
+            .ancestor_id(a, self.depths[a] - self.depths[b])
+            .unwrap();
+        if a == b {
+            return a;
+        }
+        for jumps in self.jumps.iter().rev() {
+            if jumps[a] != jumps[b] {
+                a = jumps[a];
+                b = jumps[b];
             }
         }
+        self.jumps[0][a]
+    }
+
+    /// Deepest object which both objects orbit directly or indirectly (or are).
+    pub fn lowest_common_ancestor(&self, a: &str, b: &str) -> Option<&'a str> {
+        let id = self.lca_id(*self.ids.get(a)?, *self.ids.get(b)?);
+        Some(self.names[id])
+    }
+
+    /// Number of edges on the path between the two objects.
+    pub fn distance(&self, a: &str, b: &str) -> Option<usize> {
+        let (a, b) = (*self.ids.get(a)?, *self.ids.get(b)?);
+        let lca = self.lca_id(a, b);
+        Some(self.depths[a] + self.depths[b] - 2 * self.depths[lca])
+    }
+
+    /// Number of orbital transfers to get `a` into orbit around the object `b` is orbiting.
+    pub fn transfers(&self, a: &str, b: &str) -> Option<usize> {
+        self.distance(self.parent(a)?, self.parent(b)?)
+    }
 
-        None
+    /// Renders the tree in the Graphviz DOT language with edges from centers to satellites.
+    pub fn to_dot(&self) -> String {
+        let quote = |name: &str| format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut dot = String::from("digraph orbits {\n");
+        writeln!(dot, "    {};", quote(self.root())).unwrap();
+        for (id, parent) in self.parents.iter().enumerate() {
+            if let Some(parent) = parent {
+                writeln!(
+                    dot,
+                    "    {} -> {};",
+                    quote(self.names[*parent]),
+                    quote(self.names[id])
+                )
+                .unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
     }
 }
 
@@ -95,10 +238,7 @@ impl<'a> Graph<'a> {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_num_orbits() {
-        let g = parse(
-            r#"COM)B
+    const EXAMPLE: &str = r#"COM)B
 B)C
 C)D
 D)E
@@ -108,30 +248,52 @@ G)H
 D)I
 E)J
 J)K
-K)L"#,
-        )
-        .unwrap();
-        assert_eq!(g.num_orbits(), 42);
+K)L"#;
+
+    #[test]
+    fn test_num_orbits() {
+        let tree = OrbitTree::parse(EXAMPLE).unwrap();
+        assert_eq!(tree.num_orbits(), 42);
     }
 
     #[test]
     fn test_shortest_path() {
-        let g = parse(
-            r#"COM)B
-B)C
-C)D
-D)E
-E)F
-B)G
-G)H
-D)I
-E)J
-J)K
-K)L
-K)YOU
-I)SAN"#,
-        )
-        .unwrap();
-        assert_eq!(g.shortest_path_len(Vertex("YOU"), Vertex("SAN")), Some(6));
+        let input = format!("{}\nK)YOU\nI)SAN", EXAMPLE);
+        let tree = OrbitTree::parse(&input).unwrap();
+        assert_eq!(tree.transfers("YOU", "SAN"), Some(4));
+        assert_eq!(tree.distance("YOU", "SAN"), Some(6));
+    }
+
+    #[test]
+    fn test_queries() {
+        let tree = OrbitTree::parse(EXAMPLE).unwrap();
+        assert_eq!(tree.root(), "COM");
+        assert_eq!(tree.len(), 12);
+        assert_eq!(tree.depth("L"), Some(7));
+        assert_eq!(tree.ancestor("L", 3), Some("E"));
+        assert_eq!(tree.ancestor("L", 7), Some("COM"));
+        assert_eq!(tree.ancestor("L", 8), None);
+        assert_eq!(tree.is_ancestor("D", "K"), Some(true));
+        assert_eq!(tree.is_ancestor("G", "K"), Some(false));
+        assert_eq!(tree.lowest_common_ancestor("L", "I"), Some("D"));
+        assert_eq!(tree.lowest_common_ancestor("H", "F"), Some("B"));
+        assert_eq!(tree.lowest_common_ancestor("E", "K"), Some("E"));
+        assert_eq!(tree.distance("H", "F"), Some(6));
+        assert_eq!(tree.depth("X"), None);
+
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph orbits {\n    \"COM\";\n    \"COM\" -> \"B\";\n"));
+        assert_eq!(dot.matches("->").count(), 11);
+    }
+
+    #[test]
+    fn test_validation() {
+        let err = |input| OrbitTree::parse(input).unwrap_err().to_string();
+        assert_eq!(err("COM)B\nX)B"), "B orbits both COM and X");
+        assert_eq!(err("COM)B\nX)Y"), "multiple roots: COM, X");
+        assert_eq!(err("COM)B\nC)D\nD)C"), "cycle among C, D");
+        assert_eq!(err("A)B\nB)A"), "no root: all objects orbit in cycles");
+        assert_eq!(err(""), "no orbits");
+        assert_eq!(err("COM)B)C"), "invalid orbit: COM)B)C");
     }
 }
@@ -9,13 +9,13 @@ pub fn solve(input: &str) -> Result<(i64, i64), Error> {
     let part1 = permutohedron::Heap::new(&mut init_phase)
         .filter_map(|phase| run_with_phase(&mem, phase).ok())
         .max()
-        .ok_or_else(|| "all programs failed")?;
+        .ok_or("all programs failed")?;
 
     let mut init_phase = [5, 6, 7, 8, 9];
     let part2 = permutohedron::Heap::new(&mut init_phase)
         .filter_map(|phase| run_with_feedback_loop(&mem, phase).ok())
         .max()
-        .ok_or_else(|| "all programs failed")?;
+        .ok_or("all programs failed")?;
 
     Ok((part1, part2))
 }
@@ -63,7 +63,7 @@ fn run_with_feedback_loop(mem: &[i64], phase: [i64; 5]) -> Result<i64, Error> {
     let out = connect(e_out, a_in)
         .join()
         .map_err(|_| "E thread paniced")?
-        .ok_or_else(|| "E stopped without output")?;
+        .ok_or("E stopped without output")?;
 
     Ok(out)
 }
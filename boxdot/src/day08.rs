@@ -1,39 +1,228 @@
-pub fn solve(input: &str) -> Option<(usize, String)> {
-    const WIDTH: usize = 25;
-    const HEIGHT: usize = 6;
+use crate::ocr;
 
-    let layers: Vec<&[u8]> = input.as_bytes().chunks(WIDTH * HEIGHT).collect();
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
 
-    let part1 = layers
+pub const WIDTH: usize = 25;
+pub const HEIGHT: usize = 6;
+
+pub const BLACK: u8 = 0;
+pub const WHITE: u8 = 1;
+pub const TRANSPARENT: u8 = 2;
+
+pub fn solve(input: &str) -> Result<(usize, String)> {
+    let img = SpaceImage::decode(input, WIDTH, HEIGHT)?;
+    let part1 = img.checksum().ok_or("no layers")?;
+
+    let pixels = img.render();
+    let rows: Vec<Vec<bool>> = pixels
+        .chunks(WIDTH)
+        .map(|row| row.iter().map(|&color| color == WHITE).collect())
+        .collect();
+    let art: Vec<String> = rows
         .iter()
-        .map(|layer| {
-            let num_zeroes = bytecount::count(layer, b'0');
-            let num_ones = bytecount::count(layer, b'1');
-            let num_twos = bytecount::count(layer, b'2');
-            (num_zeroes, num_ones, num_twos)
+        .map(|row| row.iter().map(|&lit| if lit { '#' } else { ' ' }).collect())
+        .collect();
+    println!("{}", art.join("\n"));
+    let part2 = ocr::recognize(&rows)?;
+
+    Ok((part1, part2))
+}
+
+/// Image in the Space Image Format, i.e. a stack of layers where the first layer is in front.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpaceImage {
+    width: usize,
+    height: usize,
+    layers: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LayerStats {
+    pub black: usize,
+    pub white: usize,
+    pub transparent: usize,
+}
+
+impl SpaceImage {
+    /// Creates an image from layers of `width * height` colors each.
+    pub fn new(width: usize, height: usize, layers: Vec<Vec<u8>>) -> Result<Self> {
+        if width == 0 || height == 0 {
+            return Err(format!("invalid image size: {}x{}", width, height).into());
+        }
+        if let Some(index) = layers.iter().position(|l| l.len() != width * height) {
+            return Err(format!("layer {} does not have {} pixels", index, width * height).into());
+        }
+        if let Some(color) = layers.iter().flatten().find(|&&c| c > 9) {
+            return Err(format!("invalid color: {}", color).into());
+        }
+        Ok(Self {
+            width,
+            height,
+            layers,
         })
-        .min_by_key(|&(num_zeroes, _, _)| num_zeroes)
-        .map(|(_, num_ones, num_twos)| num_ones * num_twos)?;
-
-    let mut img = [b'2'; WIDTH * HEIGHT];
-    for layer in layers.into_iter() {
-        for i in 0..WIDTH * HEIGHT {
-            let color = match (img[i], layer[i]) {
-                (b'0', _) => b'0',
-                (b'1', _) => b'1',
-                (b'2', x) => x,
-                _ => unreachable!(),
-            };
-            img[i] = color;
+    }
+
+    /// Decodes a string of digits; surrounding whitespace is ignored.
+    pub fn decode(data: &str, width: usize, height: usize) -> Result<Self> {
+        let digits: Vec<u8> = data
+            .trim()
+            .chars()
+            .map(|c| match c.to_digit(10) {
+                Some(d) => Ok(d as u8),
+                None => Err(format!("invalid digit: {:?}", c)),
+            })
+            .collect::<std::result::Result<_, _>>()?;
+        let layer_size = width * height;
+        if layer_size == 0 || !digits.len().is_multiple_of(layer_size) {
+            return Err(format!(
+                "data of length {} is not a multiple of the layer size {}x{}",
+                digits.len(),
+                width,
+                height
+            )
+            .into());
+        }
+        let layers = digits.chunks(layer_size).map(<[u8]>::to_vec).collect();
+        Self::new(width, height, layers)
+    }
+
+    /// Builds an image whose front-to-back composite is `target`.
+    ///
+    /// For each pixel `depths` gives the number of transparent layers in front of the visible
+    /// color. The layers behind it get the opposite color, so that stacking the layers in reverse
+    /// flips every pixel with a layer behind its visible one. Transparent pixels of the target are
+    /// transparent on all layers.
+    pub fn encode(target: &[u8], width: usize, height: usize, depths: &[usize]) -> Result<Self> {
+        if target.len() != width * height || depths.len() != target.len() {
+            return Err("target and depths must have one entry per pixel".into());
+        }
+        if let Some(color) = target.iter().find(|&&c| c > TRANSPARENT) {
+            return Err(format!("invalid target color: {}", color).into());
+        }
+        let num_layers = depths.iter().max().map_or(1, |&depth| depth + 1);
+        let layers = (0..num_layers)
+            .map(|layer| {
+                target
+                    .iter()
+                    .zip(depths)
+                    .map(|(&color, &depth)| match color {
+                        TRANSPARENT => TRANSPARENT,
+                        _ if layer < depth => TRANSPARENT,
+                        _ if layer == depth => color,
+                        _ => 1 - color,
+                    })
+                    .collect()
+            })
+            .collect();
+        Self::new(width, height, layers)
+    }
+
+    /// Serializes the image back into the digit string.
+    pub fn to_data(&self) -> String {
+        self.layers
+            .iter()
+            .flatten()
+            .map(|&color| (b'0' + color) as char)
+            .collect()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn layers(&self) -> &[Vec<u8>] {
+        &self.layers
+    }
+
+    pub fn stats(&self) -> Vec<LayerStats> {
+        self.layers
+            .iter()
+            .map(|layer| LayerStats {
+                black: bytecount::count(layer, BLACK),
+                white: bytecount::count(layer, WHITE),
+                transparent: bytecount::count(layer, TRANSPARENT),
+            })
+            .collect()
+    }
+
+    /// Number of white times number of transparent pixels on the layer with the fewest black
+    /// pixels.
+    pub fn checksum(&self) -> Option<usize> {
+        self.stats()
+            .into_iter()
+            .min_by_key(|stats| stats.black)
+            .map(|stats| stats.white * stats.transparent)
+    }
+
+    /// Stacks the layers in the given order, the first one in front.
+    pub fn composite(&self, order: &[usize]) -> Result<Vec<u8>> {
+        let mut img = vec![TRANSPARENT; self.width * self.height];
+        for &index in order {
+            let layer = self
+                .layers
+                .get(index)
+                .ok_or_else(|| format!("no such layer: {}", index))?;
+            for (color, &layer_color) in img.iter_mut().zip(layer) {
+                if *color == TRANSPARENT {
+                    *color = layer_color;
+                }
+            }
         }
+        Ok(img)
     }
 
-    let part2 = img.chunks(WIDTH).fold(String::new(), |mut msg, line| {
-        msg.push_str(std::str::from_utf8(line).unwrap());
-        msg.push('\n');
-        msg
-    });
-    println!("{}", part2);
+    /// Stacks all layers in their natural order.
+    pub fn render(&self) -> Vec<u8> {
+        let order: Vec<usize> = (0..self.layers.len()).collect();
+        self.composite(&order).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Some((part1, part2))
+    #[test]
+    fn test_decode() {
+        let img = SpaceImage::decode("123456789012", 3, 2).unwrap();
+        assert_eq!(
+            img.layers(),
+            &[vec![1, 2, 3, 4, 5, 6], vec![7, 8, 9, 0, 1, 2]][..]
+        );
+
+        let img = SpaceImage::decode("0222112222120000\n", 2, 2).unwrap();
+        assert_eq!(img.render(), vec![0, 1, 1, 0]);
+        assert_eq!(img.composite(&[3, 2, 1, 0]).unwrap(), vec![0, 0, 0, 0]);
+        assert_eq!(img.to_data(), "0222112222120000");
+        assert_eq!(
+            img.stats()[1],
+            LayerStats {
+                black: 0,
+                white: 2,
+                transparent: 2
+            }
+        );
+        assert_eq!(img.checksum(), Some(4));
+
+        assert!(SpaceImage::decode("02221", 2, 2).is_err());
+        assert!(SpaceImage::decode("0x22", 2, 2).is_err());
+        assert!(SpaceImage::encode(&[0, 3], 2, 1, &[0, 0]).is_err());
+        assert!(img.composite(&[4]).is_err());
+    }
+
+    #[test]
+    fn test_encode() {
+        let target = [1, 0, 2, 1];
+        let img = SpaceImage::encode(&target, 2, 2, &[0, 2, 1, 1]).unwrap();
+        assert_eq!(img.layers().len(), 3);
+        assert_eq!(img.render(), target);
+        assert_eq!(SpaceImage::decode(&img.to_data(), 2, 2).unwrap(), img);
+        // the second pixel is visible on the last layer and keeps its color
+        assert_eq!(img.composite(&[2, 1, 0]).unwrap(), vec![0, 0, 2, 0]);
+    }
 }
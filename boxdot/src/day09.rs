@@ -6,7 +6,7 @@ pub fn solve(input: &str) -> Result<(i64, i64), Error> {
     let mem = parse(input);
     let produce_output = |input| -> Result<i64, Error> {
         let output = run(mem.clone(), &[input])?;
-        let value = output.first().ok_or_else(|| "missing output")?;
+        let value = output.first().ok_or("missing output")?;
         Ok(*value)
     };
 
@@ -54,6 +54,12 @@ impl Memory {
         self.data.get(addr).cloned().unwrap_or(0)
     }
 
+    /// Resets the memory to the image, reusing the allocation.
+    pub fn restore(&mut self, image: &Memory) {
+        self.data.clone_from(&image.data);
+        self.relative_base = image.relative_base;
+    }
+
     pub fn write(&mut self, addr: usize, value: i64) {
         if addr >= self.data.len() {
             self.data.resize(addr + 1, 0);
@@ -134,7 +140,7 @@ pub fn execute(
     mut input: impl FnMut() -> i64,
     mut output: impl FnMut(i64),
 ) -> Result<Option<usize>, Error> {
-    let op = fetch(&mem, ip)?;
+    let op = fetch(mem, ip)?;
     let ip = match op {
         Op::Add(a, b, c) => {
             let value_a = mem.value(a);
@@ -1,129 +1,247 @@
-use itertools::iproduct;
-use std::ops::Index;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 
 type Error = Box<dyn std::error::Error>;
 type Result<T> = std::result::Result<T, Error>;
 
 pub fn solve(input: &str) -> Result<(usize, usize)> {
     let space = parse(input)?;
-    let (station_pos, max_detected) = max_detected(&space).ok_or_else(|| "no asteroids")?;
-    let (x, y) = vaporize(&space, station_pos)
-        .nth(199)
-        .ok_or_else(|| "no 200th vaporized asteroid")?;
+    let (station_pos, max_detected) = space.best_station().ok_or("no asteroids")?;
+    let (x, y) = space
+        .nth_vaporized(station_pos, &Sweep::default(), 199)
+        .ok_or("no 200th vaporized asteroid")?;
     Ok((max_detected, x * 100 + y))
 }
 
-type Coord = (usize, usize);
+pub type Coord = (usize, usize);
 
-struct Space {
-    data: Vec<bool>,
-    width: usize,
+/// Direction from one cell to another reduced to its smallest integer vector; y grows downwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Direction {
+    pub dx: i64,
+    pub dy: i64,
 }
 
-impl Space {
-    fn asteroids(&self) -> impl Iterator<Item = Coord> + '_ {
-        let height = self.data.len() / self.width;
-        iproduct!(0..self.width, 0..height).filter(move |&(x, y)| self.data[x + self.width * y])
+impl Direction {
+    /// Returns the direction and the number of its steps from `from` to `to`.
+    pub fn between(from: Coord, to: Coord) -> Option<(Direction, i64)> {
+        let dx = to.0 as i64 - from.0 as i64;
+        let dy = to.1 as i64 - from.1 as i64;
+        let gcd = num_integer::gcd(dx, dy);
+        if gcd == 0 {
+            return None;
+        }
+        let direction = Direction {
+            dx: dx / gcd,
+            dy: dy / gcd,
+        };
+        Some((direction, gcd))
     }
+}
 
-    fn is_in_sight(&self, origin: Coord, target: Coord) -> bool {
-        line(origin, target).skip(1).find(|&target| self[target]) == Some(target)
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// Rotating laser: the start direction and the sense of rotation as seen on the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sweep {
+    start: (i64, i64),
+    rotation: Rotation,
 }
 
-impl Index<Coord> for Space {
-    type Output = bool;
-    fn index(&self, (x, y): Coord) -> &Self::Output {
-        &self.data[x + y * self.width]
+impl Default for Sweep {
+    /// Starts pointing up and rotates clockwise.
+    fn default() -> Self {
+        Self {
+            start: (0, -1),
+            rotation: Rotation::Clockwise,
+        }
     }
 }
 
-fn parse(input: &str) -> Result<Space> {
-    let data: Result<_> = input
-        .lines()
-        .map(|l| {
-            l.bytes().map(|c| match c {
-                b'.' => Ok(false),
-                b'#' => Ok(true),
-                _ => Err(format!("unexpected char: {}", c).into()),
-            })
+impl Sweep {
+    /// The start direction may be any non-zero vector.
+    pub fn new((dx, dy): (i64, i64), rotation: Rotation) -> Result<Self> {
+        if (dx, dy) == (0, 0) {
+            return Err("start direction must not be zero".into());
+        }
+        Ok(Self {
+            start: (dx, dy),
+            rotation,
         })
-        .flatten()
-        .collect();
-    Ok(Space {
-        data: data?,
-        width: input.lines().next().ok_or_else(|| "empty input")?.len(),
-    })
+    }
+
+    /// Cross product oriented such that it is positive if `b` comes less than half a turn after
+    /// `a`.
+    fn cross(&self, (ax, ay): (i64, i64), (bx, by): (i64, i64)) -> i64 {
+        // with y growing downwards, a positive cross product turns clockwise on the map
+        let cross = ax * by - ay * bx;
+        match self.rotation {
+            Rotation::Clockwise => cross,
+            Rotation::CounterClockwise => -cross,
+        }
+    }
+
+    /// Whether the laser passes the direction in the first half of its turn, including the
+    /// start itself.
+    fn is_first_half(&self, d: Direction) -> bool {
+        let d = (d.dx, d.dy);
+        let cross = self.cross(self.start, d);
+        let dot = self.start.0 * d.0 + self.start.1 * d.1;
+        cross > 0 || (cross == 0 && dot > 0)
+    }
+
+    /// Orders directions by the angle the laser has to turn to reach them, in exact integers.
+    pub fn cmp(&self, a: Direction, b: Direction) -> Ordering {
+        let (half_a, half_b) = (!self.is_first_half(a), !self.is_first_half(b));
+        half_a
+            .cmp(&half_b)
+            .then_with(|| 0.cmp(&self.cross((a.dx, a.dy), (b.dx, b.dy))))
+    }
 }
 
-fn max_detected(space: &Space) -> Option<(Coord, usize)> {
-    let asteroids: Vec<Coord> = space.asteroids().collect();
-    asteroids
-        .iter()
-        .map(|&origin| {
-            let detected = asteroids
-                .iter()
-                .filter(|&&target| space.is_in_sight(origin, target))
-                .count();
-            (origin, detected)
-        })
-        .max_by_key(|&(_, detected)| detected)
+pub struct Space {
+    data: Vec<bool>,
+    width: usize,
 }
 
-fn vaporize<'a>(space: &'a Space, station_pos: Coord) -> impl Iterator<Item = Coord> + 'a {
-    let angle = |(x0, y0), (x1, y1)| {
-        let dx = x1 as f64 - x0 as f64;
-        let dy = -(y1 as f64 - y0 as f64); // mirrored y-axis
-        let mut angle = dx.atan2(dy);
-        while angle < 0.0 {
-            angle += 2. * std::f64::consts::PI;
+impl Space {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.data.len() / self.width
+    }
+
+    pub fn is_asteroid(&self, (x, y): Coord) -> bool {
+        x < self.width && self.data.get(x + y * self.width) == Some(&true)
+    }
+
+    pub fn asteroids(&self) -> impl Iterator<Item = Coord> + '_ {
+        let width = self.width;
+        self.data
+            .iter()
+            .enumerate()
+            .filter(|(_, &asteroid)| asteroid)
+            .map(move |(index, _)| (index % width, index / width))
+    }
+
+    /// Asteroids seen from `origin` grouped by direction, nearest first in each group.
+    pub fn groups(&self, origin: Coord) -> HashMap<Direction, Vec<Coord>> {
+        let mut groups: HashMap<Direction, Vec<(i64, Coord)>> = HashMap::new();
+        for target in self.asteroids() {
+            if let Some((direction, steps)) = Direction::between(origin, target) {
+                groups.entry(direction).or_default().push((steps, target));
+            }
         }
-        angle
-    };
-
-    let mut asteroids: Vec<Coord> = space.asteroids().collect();
-    asteroids.sort_unstable_by(|&target0, &target1| {
-        let alpha0 = angle(station_pos, target0);
-        let alpha1 = angle(station_pos, target1);
-        alpha0.partial_cmp(&alpha1).unwrap()
-    });
-
-    std::iter::from_fn(move || {
-        asteroids
+        groups
+            .into_iter()
+            .map(|(direction, mut targets)| {
+                targets.sort_unstable();
+                let targets = targets.into_iter().map(|(_, target)| target).collect();
+                (direction, targets)
+            })
+            .collect()
+    }
+
+    /// Number of asteroids in direct sight from any cell, not counting an asteroid at the cell.
+    pub fn visible_from(&self, origin: Coord) -> usize {
+        self.groups(origin).len()
+    }
+
+    /// Number of visible asteroids from each cell, row by row.
+    pub fn visibility_map(&self) -> Vec<usize> {
+        (0..self.data.len())
+            .map(|index| self.visible_from((index % self.width, index / self.width)))
+            .collect()
+    }
+
+    /// Asteroid from which the most other asteroids are visible; the first one in reading order
+    /// on ties.
+    pub fn best_station(&self) -> Option<(Coord, usize)> {
+        self.asteroids()
+            .map(|station| (station, self.visible_from(station)))
+            .fold(None, |best, (station, count)| match best {
+                Some((_, best_count)) if best_count >= count => best,
+                _ => Some((station, count)),
+            })
+    }
+
+    /// All other asteroids in the order the laser at `station` vaporizes them.
+    pub fn vaporization_order(&self, station: Coord, sweep: &Sweep) -> Vec<Coord> {
+        let mut groups: Vec<(Direction, Vec<Coord>)> = self.groups(station).into_iter().collect();
+        groups.sort_unstable_by(|(a, _), (b, _)| sweep.cmp(*a, *b));
+        // the i-th asteroid of every direction falls in the i-th rotation
+        let mut order: Vec<(usize, usize, Coord)> = groups
             .iter()
-            .position(|&target| space.is_in_sight(station_pos, target))
-            .map(|target_index| asteroids.remove(target_index))
-    })
+            .enumerate()
+            .flat_map(|(rank, (_, targets))| {
+                targets
+                    .iter()
+                    .enumerate()
+                    .map(move |(rotation, &target)| (rotation, rank, target))
+            })
+            .collect();
+        order.sort_unstable();
+        order.into_iter().map(|(_, _, target)| target).collect()
+    }
+
+    /// The asteroid vaporized after `n` others.
+    pub fn nth_vaporized(&self, station: Coord, sweep: &Sweep, n: usize) -> Option<Coord> {
+        self.vaporization_order(station, sweep).get(n).copied()
+    }
 }
 
-fn line((x0, y0): Coord, (x1, y1): Coord) -> impl Iterator<Item = Coord> {
-    let mut dx = x1 as isize - x0 as isize;
-    let mut dy = y1 as isize - y0 as isize;
-    let gcd = num_integer::gcd(dx, dy);
-    if gcd != 0 {
-        dx /= gcd;
-        dy /= gcd;
-    }
-
-    std::iter::successors(Some((x0, y0)), move |&(x, y)| {
-        if (x, y) == (x1, y1) {
-            None
-        } else {
-            let x = (x as isize + dx) as usize;
-            let y = (y as isize + dy) as usize;
-            if (x, y) == (x0, y0) {
-                None
-            } else {
-                Some((x, y))
+fn parse(input: &str) -> Result<Space> {
+    let width = input.lines().next().ok_or("empty input")?.len();
+    if width == 0 {
+        return Err("empty first row".into());
+    }
+    let mut data = Vec::new();
+    for l in input.lines() {
+        if l.len() != width {
+            return Err(format!("row of length {} differs from width {}", l.len(), width).into());
+        }
+        for c in l.bytes() {
+            match c {
+                b'.' => data.push(false),
+                b'#' => data.push(true),
+                _ => return Err(format!("unexpected char: {}", c).into()),
             }
         }
-    })
+    }
+    Ok(Space { data, width })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const LARGE: &str = r#".#..##.###...#######
+##.############..##.
+.#.######.########.#
+.###.#######.####.#.
+#####.##.#.##.###.##
+..#####..#.#########
+####################
+#.####....###.#.#.##
+##.#################
+#####.##.###..####..
+..######..##.#######
+####.##.####...##..#
+.#####..#.######.###
+##...#.##########...
+#.##########.#######
+.####.#.###.###.#.##
+....##.##.###..#####
+.#.#.###########.###
+#.#.#.#####.####.###
+###.##.####.##.#..##"#;
+
     #[test]
     fn test_max_detected() {
         let space = parse(
@@ -134,7 +252,15 @@ mod tests {
 ...##"#,
         )
         .unwrap();
-        assert_eq!(max_detected(&space).unwrap().1, 8);
+        assert_eq!(space.best_station(), Some(((3, 4), 8)));
+        let counts: Vec<usize> = space
+            .visibility_map()
+            .into_iter()
+            .zip(&space.data)
+            .filter(|(_, &asteroid)| asteroid)
+            .map(|(count, _)| count)
+            .collect();
+        assert_eq!(counts, vec![7, 7, 6, 7, 7, 7, 5, 7, 8, 7]);
 
         let space = parse(
             r#"......#.#.
@@ -149,7 +275,7 @@ mod tests {
 .#....####"#,
         )
         .unwrap();
-        assert_eq!(max_detected(&space).unwrap().1, 33);
+        assert_eq!(space.best_station(), Some(((5, 8), 33)));
 
         let space = parse(
             r#"#.#...#.#.
@@ -164,7 +290,7 @@ mod tests {
 .####.###."#,
         )
         .unwrap();
-        assert_eq!(max_detected(&space).unwrap().1, 35);
+        assert_eq!(space.best_station(), Some(((1, 2), 35)));
 
         let space = parse(
             r#".#..#..###
@@ -179,32 +305,10 @@ mod tests {
 .....#.#.."#,
         )
         .unwrap();
-        assert_eq!(max_detected(&space).unwrap().1, 41);
+        assert_eq!(space.best_station(), Some(((6, 3), 41)));
 
-        let space = parse(
-            r#".#..##.###...#######
-##.############..##.
-.#.######.########.#
-.###.#######.####.#.
-#####.##.#.##.###.##
-..#####..#.#########
-####################
-#.####....###.#.#.##
-##.#################
-#####.##.###..####..
-..######..##.#######
-####.##.####...##..#
-.#####..#.######.###
-##...#.##########...
-#.##########.#######
-.####.#.###.###.#.##
-....##.##.###..#####
-.#.#.###########.###
-#.#.#.#####.####.###
-###.##.####.##.#..##"#,
-        )
-        .unwrap();
-        assert_eq!(max_detected(&space).unwrap().1, 210);
+        let space = parse(LARGE).unwrap();
+        assert_eq!(space.best_station(), Some(((11, 13), 210)));
     }
 
     #[test]
@@ -217,37 +321,29 @@ mod tests {
 ..#.#.....#....##"#,
         )
         .unwrap();
-        let asteroids: Vec<(usize, usize)> = vaporize(&space, (8, 3)).collect();
-        println!("{:?}", asteroids);
-        assert_eq!(asteroids[0], (8, 1));
-        assert_eq!(asteroids[1], (9, 0));
+        let asteroids = space.vaporization_order((8, 3), &Sweep::default());
+        assert_eq!(&asteroids[..3], &[(8, 1), (9, 0), (9, 1)]);
 
-        let space = parse(
-            r#".#..##.###...#######
-##.############..##.
-.#.######.########.#
-.###.#######.####.#.
-#####.##.#.##.###.##
-..#####..#.#########
-####################
-#.####....###.#.#.##
-##.#################
-#####.##.###..####..
-..######..##.#######
-####.##.####...##..#
-.#####..#.######.###
-##...#.##########...
-#.##########.#######
-.####.#.###.###.#.##
-....##.##.###..#####
-.#.#.###########.###
-#.#.#.#####.####.###
-###.##.####.##.#..##"#,
-        )
-        .unwrap();
-        let asteroids: Vec<(usize, usize)> = vaporize(&space, (11, 13)).collect();
-        assert_eq!(asteroids[0], (11, 12));
-        assert_eq!(asteroids[1], (12, 1));
-        assert_eq!(asteroids[2], (12, 2));
+        let space = parse(LARGE).unwrap();
+        let asteroids = space.vaporization_order((11, 13), &Sweep::default());
+        assert_eq!(&asteroids[..3], &[(11, 12), (12, 1), (12, 2)]);
+        assert_eq!(asteroids[199], (8, 2));
+        assert_eq!(asteroids[298], (11, 1));
+        assert_eq!(asteroids.len(), 299);
+    }
+
+    #[test]
+    fn test_sweep() {
+        let space = parse("#.#\n.#.\n#.#").unwrap();
+        let corners = |sweep| space.vaporization_order((1, 1), &sweep);
+        assert_eq!(
+            corners(Sweep::default()),
+            vec![(2, 0), (2, 2), (0, 2), (0, 0)]
+        );
+        let sweep = Sweep::new((-1, -1), Rotation::CounterClockwise).unwrap();
+        assert_eq!(corners(sweep), vec![(0, 0), (0, 2), (2, 2), (2, 0)]);
+        let sweep = Sweep::new((0, 5), Rotation::Clockwise).unwrap();
+        assert_eq!(corners(sweep), vec![(0, 2), (0, 0), (2, 0), (2, 2)]);
+        assert!(Sweep::new((0, 0), Rotation::Clockwise).is_err());
     }
 }
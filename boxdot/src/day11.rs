@@ -1,4 +1,6 @@
 use crate::day09::{execute, parse, Memory};
+use crate::ocr;
+use crate::tui::Canvas;
 use std::collections::HashMap;
 
 type Error = Box<dyn std::error::Error>;
@@ -10,12 +12,24 @@ pub fn solve(input: &str) -> Result<(usize, String)> {
     let part1 = field.len();
 
     let field = paint(mem, 1)?;
-    let message = into_message(&field);
-    println!("{}", message);
+    let canvas = into_canvas(&field);
+    println!(
+        "{}",
+        canvas.render(&|&color: &i64| if color == 1 { 'X' } else { ' ' })
+    );
+    let message = ocr::recognize_canvas(&canvas, |&color| color == 1)?;
 
     Ok((part1, message))
 }
 
+/// Paints the registration identifier, i.e. starting on a white panel.
+///
+/// Returns the color of each painted panel with the y-axis pointing downwards.
+pub fn registration(input: &str) -> Result<Canvas<i64>> {
+    let field = paint(parse(input), 1)?;
+    Ok(into_canvas(&field))
+}
+
 type Coord = (isize, isize);
 
 fn paint(mut mem: Memory, init_color: i64) -> Result<HashMap<Coord, i64>> {
@@ -49,26 +63,10 @@ fn paint(mut mem: Memory, init_color: i64) -> Result<HashMap<Coord, i64>> {
     Ok(field)
 }
 
-fn into_message(field: &HashMap<Coord, i64>) -> String {
-    use std::isize::{MAX, MIN};
-    let (l, r, t, b) = field
+fn into_canvas(field: &HashMap<Coord, i64>) -> Canvas<i64> {
+    // the robot's y-axis points upwards, the canvas' one downwards
+    field
         .iter()
-        .fold((MAX, MIN, MIN, MAX), |(l, r, t, b), (&(x, y), _)| {
-            (l.min(x), r.max(x), t.max(y), b.min(y))
-        });
-    (b..=t)
-        .rev()
-        .map(|y| {
-            (l..=r)
-                .map(|x| {
-                    if field.get(&(x, y)) == Some(&1) {
-                        'X'
-                    } else {
-                        ' '
-                    }
-                })
-                .collect()
-        })
-        .collect::<Vec<String>>()
-        .join("\n")
+        .map(|(&(x, y), &color)| ((x as i64, -(y as i64)), color))
+        .collect()
 }
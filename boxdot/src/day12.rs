@@ -1,121 +1,265 @@
 use num_integer::lcm;
 use std::cmp::Ordering;
-use std::collections::{hash_map::Entry, HashMap};
+use std::io::Write;
 
-pub fn solve(input: &str) -> (i64, usize) {
-    let init_config = parse(input);
-    let config = simulate_motion(&init_config).nth(1_000).unwrap();
-    (total_energy(&config), period(&init_config))
-}
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
 
-fn parse(input: &str) -> Configuration {
-    let positions = input.lines().map(|l| {
-        let mut coords = l[1..l.len() - 1].split(',').map(|elem| {
-            let coord: i64 = elem.trim().split('=').nth(1).unwrap().parse().unwrap();
-            coord
-        });
-        let x = coords.next().unwrap();
-        let y = coords.next().unwrap();
-        let z = coords.next().unwrap();
-        [x, y, z]
-    });
-
-    let mut pos = [[0; 4]; 3];
-    for (i, p) in positions.enumerate() {
-        for axis in 0..3 {
-            pos[axis][i] = p[axis];
-        }
-    }
-    let vel = [[0; 4]; 3];
+pub fn solve(input: &str) -> Result<(i64, usize)> {
+    let init_config = parse(input)?;
+    let config = init_config.simulate().nth(1_000).unwrap();
+    Ok((config.total_energy(), period(&init_config)))
+}
 
-    Configuration { pos, vel }
+/// Parses one body per line given as `<x=1, y=2, ...>` with any number of coordinates.
+pub fn parse(input: &str) -> Result<Configuration> {
+    let positions = input
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(|l| {
+            let inner = l
+                .strip_prefix('<')
+                .and_then(|l| l.strip_suffix('>'))
+                .ok_or_else(|| format!("invalid body: {}", l))?;
+            inner
+                .split(',')
+                .map(|elem| {
+                    let value = elem.split('=').nth(1).unwrap_or(elem);
+                    value
+                        .trim()
+                        .parse()
+                        .map_err(|e| format!("invalid coordinate {:?}: {}", elem, e).into())
+                })
+                .collect::<Result<Vec<i64>>>()
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Configuration::new(&positions)
 }
 
-// we store transposed matrix since we simulate each axis independently
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct Configuration {
-    pos: [[i64; 4]; 3],
-    vel: [[i64; 4]; 3],
+/// Positions and velocities of all bodies along one axis.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Axis {
+    pub pos: Vec<i64>,
+    pub vel: Vec<i64>,
 }
 
-fn simulate_motion_for_axis(
-    pos: [i64; 4],
-    vel: [i64; 4],
-) -> impl Iterator<Item = ([i64; 4], [i64; 4])> {
-    std::iter::successors(Some((pos, vel)), |(pos, vel)| {
-        let mut pos = *pos;
-        let mut vel = *vel;
-        for i in 0..4 {
-            for j in i + 1..4 {
-                match pos[i].cmp(&pos[j]) {
+impl Axis {
+    fn step(&mut self) {
+        let n = self.pos.len();
+        for i in 0..n {
+            for j in i + 1..n {
+                match self.pos[i].cmp(&self.pos[j]) {
                     Ordering::Less => {
-                        vel[i] += 1;
-                        vel[j] -= 1;
+                        self.vel[i] += 1;
+                        self.vel[j] -= 1;
                     }
-
                     Ordering::Greater => {
-                        vel[i] -= 1;
-                        vel[j] += 1;
+                        self.vel[i] -= 1;
+                        self.vel[j] += 1;
                     }
                     Ordering::Equal => {}
                 }
             }
         }
-        for i in 0..4 {
-            pos[i] += vel[i];
+        for (pos, vel) in self.pos.iter_mut().zip(&self.vel) {
+            *pos += vel;
         }
-        Some((pos, vel))
-    })
+    }
+
+    pub fn simulate(&self) -> impl Iterator<Item = Axis> {
+        std::iter::successors(Some(self.clone()), |axis| {
+            let mut axis = axis.clone();
+            axis.step();
+            Some(axis)
+        })
+    }
 }
 
-fn simulate_motion(config: &Configuration) -> impl Iterator<Item = Configuration> {
-    let motion_x = simulate_motion_for_axis(config.pos[0], config.vel[0]);
-    let motion_y = simulate_motion_for_axis(config.pos[1], config.vel[1]);
-    let motion_z = simulate_motion_for_axis(config.pos[2], config.vel[2]);
-    motion_x.zip(motion_y.zip(motion_z)).map(
-        |((pos_x, vel_x), ((pos_y, vel_y), (pos_z, vel_z)))| Configuration {
-            pos: [pos_x, pos_y, pos_z],
-            vel: [vel_x, vel_y, vel_z],
-        },
-    )
+// we store transposed matrix since we simulate each axis independently
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Configuration {
+    axes: Vec<Axis>,
 }
 
-fn total_energy(config: &Configuration) -> i64 {
-    (0..4)
-        .map(|i| {
-            let pot: i64 = config.pos.iter().map(|x| x[i].abs()).sum();
-            let kin: i64 = config.vel.iter().map(|x| x[i].abs()).sum();
-            pot * kin
+impl Configuration {
+    /// Creates a system of resting bodies; all positions must have the same number of coordinates.
+    pub fn new(positions: &[Vec<i64>]) -> Result<Self> {
+        let num_dims = positions.first().map_or(0, Vec::len);
+        if num_dims == 0 {
+            return Err("no bodies or no coordinates".into());
+        }
+        if let Some(body) = positions.iter().position(|p| p.len() != num_dims) {
+            return Err(format!("body {} does not have {} coordinates", body, num_dims).into());
+        }
+        let axes = (0..num_dims)
+            .map(|axis| Axis {
+                pos: positions.iter().map(|p| p[axis]).collect(),
+                vel: vec![0; positions.len()],
+            })
+            .collect();
+        Ok(Self { axes })
+    }
+
+    pub fn num_bodies(&self) -> usize {
+        self.axes[0].pos.len()
+    }
+
+    pub fn num_dims(&self) -> usize {
+        self.axes.len()
+    }
+
+    pub fn axes(&self) -> &[Axis] {
+        &self.axes
+    }
+
+    pub fn position(&self, body: usize) -> Vec<i64> {
+        self.axes.iter().map(|axis| axis.pos[body]).collect()
+    }
+
+    pub fn velocity(&self, body: usize) -> Vec<i64> {
+        self.axes.iter().map(|axis| axis.vel[body]).collect()
+    }
+
+    pub fn step(&mut self) {
+        for axis in &mut self.axes {
+            axis.step();
+        }
+    }
+
+    /// Iterates over the configuration and all following ones.
+    pub fn simulate(&self) -> impl Iterator<Item = Configuration> {
+        std::iter::successors(Some(self.clone()), |config| {
+            let mut config = config.clone();
+            config.step();
+            Some(config)
         })
-        .sum()
-}
+    }
 
-/// Finds and returns fixpoint as (offset, period).
-fn fixpoint(config: &Configuration, axis: usize) -> (usize, usize) {
-    let mut states = HashMap::new();
-    for (i, (pos, vel)) in simulate_motion_for_axis(config.pos[axis], config.vel[axis]).enumerate()
-    {
-        match states.entry((pos, vel)) {
-            Entry::Occupied(entry) => {
-                let offset = *entry.get();
-                let period = i - offset;
-                return (offset, period);
-            }
-            Entry::Vacant(entry) => {
-                entry.insert(i);
+    pub fn total_energy(&self) -> i64 {
+        (0..self.num_bodies())
+            .map(|i| {
+                let pot: i64 = self.axes.iter().map(|axis| axis.pos[i].abs()).sum();
+                let kin: i64 = self.axes.iter().map(|axis| axis.vel[i].abs()).sum();
+                pot * kin
+            })
+            .sum()
+    }
+
+    pub fn energy_after(&self, steps: usize) -> i64 {
+        self.simulate().nth(steps).unwrap().total_energy()
+    }
+
+    /// Streams the first `steps + 1` configurations as CSV with one row per body and step.
+    pub fn write_history(&self, steps: usize, mut w: impl Write) -> Result<()> {
+        let names: Vec<String> = (0..self.num_dims()).map(axis_name).collect();
+        write!(w, "step,body")?;
+        for name in &names {
+            write!(w, ",{}", name)?;
+        }
+        for name in &names {
+            write!(w, ",v{}", name)?;
+        }
+        writeln!(w)?;
+
+        for (step, config) in self.simulate().take(steps + 1).enumerate() {
+            for body in 0..config.num_bodies() {
+                write!(w, "{},{}", step, body)?;
+                for value in config.position(body).iter().chain(&config.velocity(body)) {
+                    write!(w, ",{}", value)?;
+                }
+                writeln!(w)?;
             }
         }
+        Ok(())
     }
-    unreachable!();
 }
 
-fn period(config: &Configuration) -> usize {
-    let (offset_x, period_x) = fixpoint(&config, 0);
-    let (offset_y, period_y) = fixpoint(&config, 1);
-    let (offset_z, period_z) = fixpoint(&config, 2);
-    assert_eq!(offset_x, offset_y);
-    assert_eq!(offset_y, offset_z);
-    lcm(lcm(period_x, period_y), period_z)
+fn axis_name(axis: usize) -> String {
+    match axis {
+        0 => "x".into(),
+        1 => "y".into(),
+        2 => "z".into(),
+        _ => format!("a{}", axis),
+    }
+}
+
+/// Eventually periodic sequence: after `tail` states, the next `length` states repeat forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub tail: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    /// Cycle of several sequences advancing in lockstep.
+    ///
+    /// All of them are periodic once the longest tail is over, and then repeat together after
+    /// the least common multiple of their lengths.
+    pub fn combine(cycles: impl IntoIterator<Item = Cycle>) -> Cycle {
+        cycles
+            .into_iter()
+            .fold(Cycle { tail: 0, length: 1 }, |acc, cycle| Cycle {
+                tail: acc.tail.max(cycle.tail),
+                length: lcm(acc.length, cycle.length),
+            })
+    }
+
+    /// Number of steps until the first state which matches a previous one.
+    pub fn first_repetition(&self) -> usize {
+        self.tail + self.length
+    }
+}
+
+/// Detects the cycle of `start`, `f(start)`, `f(f(start))`, ... with Brent's algorithm, keeping
+/// only a constant number of states in memory.
+pub fn brent<T: Clone + Eq>(start: T, f: impl Fn(&T) -> T) -> Cycle {
+    // find the cycle length by letting the hare run ahead of the tortoise in powers of two
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = start.clone();
+    let mut hare = f(&start);
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        hare = f(&hare);
+        length += 1;
+    }
+
+    // with the hare one cycle length ahead, both meet at the start of the cycle
+    let mut tortoise = start.clone();
+    let mut hare = start;
+    for _ in 0..length {
+        hare = f(&hare);
+    }
+    let mut tail = 0;
+    while tortoise != hare {
+        tortoise = f(&tortoise);
+        hare = f(&hare);
+        tail += 1;
+    }
+
+    Cycle { tail, length }
+}
+
+fn axis_cycle(axis: &Axis) -> Cycle {
+    brent(axis.clone(), |axis| {
+        let mut axis = axis.clone();
+        axis.step();
+        axis
+    })
+}
+
+/// Cycle of the whole system, found by detecting the cycle of each axis independently.
+pub fn cycle(config: &Configuration) -> Cycle {
+    Cycle::combine(config.axes.iter().map(axis_cycle))
+}
+
+pub fn period(config: &Configuration) -> usize {
+    cycle(config).first_repetition()
 }
 
 #[cfg(test)]
@@ -129,16 +273,14 @@ mod tests {
 <x=2, y=-10, z=-7>
 <x=4, y=-8, z=8>
 <x=3, y=5, z=-1>"#,
-        );
-        let config = simulate_motion(&init_config).nth(10).unwrap();
-        assert_eq!(
-            config,
-            Configuration {
-                pos: [[2, 1, 3, 2], [1, -8, -6, 0], [-3, 0, 1, 4]],
-                vel: [[-3, -1, 3, 1], [-2, 1, 2, -1], [1, 3, -3, -1]]
-            }
-        );
-        assert_eq!(total_energy(&config), 179);
+        )
+        .unwrap();
+        let config = init_config.simulate().nth(10).unwrap();
+        assert_eq!(config.position(0), vec![2, 1, -3]);
+        assert_eq!(config.velocity(3), vec![1, -1, -1]);
+        assert_eq!(config.axes()[1].pos, vec![1, -8, -6, 0]);
+        assert_eq!(config.total_energy(), 179);
+        assert_eq!(init_config.energy_after(10), 179);
         assert_eq!(period(&init_config), 2772);
     }
 
@@ -149,7 +291,52 @@ mod tests {
 <x=5, y=5, z=10>
 <x=2, y=-7, z=3>
 <x=9, y=-8, z=-3>"#,
-        );
+        )
+        .unwrap();
         assert_eq!(period(&init_config), 4686774924);
     }
+
+    #[test]
+    fn test_dims_and_history() {
+        let config =
+            parse("<x=0, y=1, z=2, w=3>\n<x=2, y=1, z=0, w=3>\n<x=1, y=1, z=1, w=1>").unwrap();
+        assert_eq!((config.num_bodies(), config.num_dims()), (3, 4));
+        assert_eq!(
+            config.simulate().nth(1).unwrap().velocity(0),
+            vec![2, 0, -2, -1]
+        );
+        assert!(parse("<x=1, y=2>\n<x=1>").is_err());
+
+        let config = Configuration::new(&[vec![0], vec![3]]).unwrap();
+        assert_eq!(period(&config), 8);
+        let mut csv = Vec::new();
+        config.write_history(1, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "step,body,x,vx\n0,0,0,0\n0,1,3,0\n1,0,1,1\n1,1,2,-1\n"
+        );
+    }
+
+    #[test]
+    fn test_cycle_with_tail() {
+        // 0, 1, 2, 5, 26, 77, 30, 1, 2, ... enters the cycle after the first state
+        let cycle = brent(0u64, |x| (x * x + 1) % 100);
+        assert_eq!(cycle, Cycle { tail: 1, length: 6 });
+        assert_eq!(brent(7, |&x| x), Cycle { tail: 0, length: 1 });
+
+        let cycles = [
+            Cycle { tail: 3, length: 4 },
+            Cycle { tail: 0, length: 6 },
+            Cycle { tail: 5, length: 1 },
+        ];
+        let combined = Cycle::combine(cycles.iter().copied());
+        assert_eq!(
+            combined,
+            Cycle {
+                tail: 5,
+                length: 12
+            }
+        );
+        assert_eq!(combined.first_repetition(), 17);
+    }
 }
@@ -1,75 +1,327 @@
 use crate::day09::{execute, parse, Memory};
-use std::collections::HashMap;
+use crate::tui::Canvas;
+use std::convert::TryFrom;
+
+pub mod replay;
+
+use replay::Recording;
 
 type Error = Box<dyn std::error::Error>;
 type Result<T> = std::result::Result<T, Error>;
 
-pub fn solve(input: &str) -> Result<usize> {
+pub fn solve(input: &str) -> Result<(usize, i64)> {
     let mem = parse(input);
-    let tiles = collect_tiles(mem.clone())?;
-    print_tiles(&tiles);
 
-    let num_block_tiles = tiles.values().filter(|&&id| id == 2).count();
+    let mut arcade = Arcade::new(mem.clone());
+    arcade.run(&mut Neutral)?;
+    print_tiles(arcade.board());
+    let num_block_tiles = arcade.board().num_blocks();
+
+    let outcome = Arcade::with_quarters(mem, 2).run(&mut FollowBall)?;
+
+    Ok((num_block_tiles, outcome.score))
+}
+
+pub type Coord = (i64, i64);
 
-    play(mem)?;
+const SCORE_POS: Coord = (-1, 0);
 
-    Ok(num_block_tiles)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tile {
+    Empty,
+    Wall,
+    Block,
+    Paddle,
+    Ball,
 }
 
-type Coord = (usize, usize);
+impl TryFrom<i64> for Tile {
+    type Error = Error;
+    fn try_from(value: i64) -> Result<Self> {
+        match value {
+            0 => Ok(Tile::Empty),
+            1 => Ok(Tile::Wall),
+            2 => Ok(Tile::Block),
+            3 => Ok(Tile::Paddle),
+            4 => Ok(Tile::Ball),
+            otherwise => Err(format!("invalid tile id: {}", otherwise).into()),
+        }
+    }
+}
 
-fn collect_tiles(mut mem: Memory) -> Result<HashMap<Coord, i64>> {
-    let mut tiles: HashMap<Coord, _> = HashMap::new();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Joystick {
+    Left,
+    Neutral,
+    Right,
+}
 
-    let mut ip = Some(0);
-    let mut output = Vec::new();
-    while let Some(next_ip) = ip {
-        ip = execute(&mut mem, next_ip, || 0, |value| output.push(value))?;
-        if output.len() == 3 {
-            let pos = (output[0] as usize, output[1] as usize);
-            let tile_id = output[2];
-            tiles.insert(pos, tile_id);
-            output.clear();
+impl Joystick {
+    pub fn value(self) -> i64 {
+        match self {
+            Joystick::Left => -1,
+            Joystick::Neutral => 0,
+            Joystick::Right => 1,
         }
     }
+}
+
+/// Screen of the arcade cabinet as assembled from the tile stream.
+#[derive(Debug, Clone, Default)]
+pub struct Board {
+    tiles: Canvas<Tile>,
+    score: i64,
+    ball: Option<Coord>,
+    paddle: Option<Coord>,
+}
+
+impl Board {
+    /// Applies a single `(x, y, value)` triple of the output stream.
+    pub fn update(&mut self, pos: Coord, value: i64) -> Result<()> {
+        if pos == SCORE_POS {
+            self.score = value;
+            return Ok(());
+        }
+
+        let tile = Tile::try_from(value)?;
+        match tile {
+            Tile::Ball => self.ball = Some(pos),
+            Tile::Paddle => self.paddle = Some(pos),
+            _ => {
+                if self.ball == Some(pos) {
+                    self.ball = None;
+                }
+                if self.paddle == Some(pos) {
+                    self.paddle = None;
+                }
+            }
+        }
+        self.tiles.set(pos, tile);
+        Ok(())
+    }
+
+    pub fn get(&self, pos: Coord) -> Tile {
+        self.tiles.get(pos).copied().unwrap_or(Tile::Empty)
+    }
+
+    pub fn tiles(&self) -> &Canvas<Tile> {
+        &self.tiles
+    }
+
+    pub fn score(&self) -> i64 {
+        self.score
+    }
+
+    pub fn ball(&self) -> Option<Coord> {
+        self.ball
+    }
+
+    pub fn paddle(&self) -> Option<Coord> {
+        self.paddle
+    }
+
+    pub fn num_blocks(&self) -> usize {
+        self.tiles
+            .values()
+            .filter(|&&tile| tile == Tile::Block)
+            .count()
+    }
+}
+
+/// Decides on the joystick position each time the game asks for input.
+pub trait Controller {
+    fn joystick(&mut self, board: &Board) -> Joystick;
+}
+
+impl<F: FnMut(&Board) -> Joystick> Controller for F {
+    fn joystick(&mut self, board: &Board) -> Joystick {
+        self(board)
+    }
+}
+
+/// Never touches the joystick.
+pub struct Neutral;
 
-    Ok(tiles)
+impl Controller for Neutral {
+    fn joystick(&mut self, _board: &Board) -> Joystick {
+        Joystick::Neutral
+    }
 }
 
-fn play(mut mem: Memory) -> Result<()> {
-    mem.write(0, 2);
+/// Moves the paddle towards the current column of the ball.
+pub struct FollowBall;
 
-    let mut ip = Some(0);
-    let mut output = Vec::new();
-    while let Some(next_ip) = ip {
-        ip = execute(&mut mem, next_ip, || 1, |value| output.push(value))?;
+impl Controller for FollowBall {
+    fn joystick(&mut self, board: &Board) -> Joystick {
+        match (board.ball(), board.paddle()) {
+            (Some((ball_x, _)), Some((paddle_x, _))) if ball_x < paddle_x => Joystick::Left,
+            (Some((ball_x, _)), Some((paddle_x, _))) if ball_x > paddle_x => Joystick::Right,
+            _ => Joystick::Neutral,
+        }
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    pub score: i64,
+    /// Number of joystick inputs consumed by the game.
+    pub steps: usize,
+    pub blocks_remaining: usize,
+}
 
-    Ok(())
+/// Headless arcade cabinet driving the Intcode game.
+#[derive(Debug, Clone)]
+pub struct Arcade {
+    mem: Memory,
+    ip: Option<usize>,
+    board: Board,
+    output: Vec<i64>,
+    steps: usize,
+    recording: Option<Recording>,
 }
 
-fn print_tiles(tiles: &HashMap<Coord, i64>) {
-    use std::usize::{MAX, MIN};
-    let (min, max) = tiles
-        .keys()
-        .fold(((MAX, MAX), (MIN, MIN)), |(min, max), pos| {
-            let min = (min.0.min(pos.0), min.1.min(pos.1));
-            let max = (max.0.max(pos.0), max.1.max(pos.1));
-            (min, max)
-        });
-    let mut s = String::new();
-    for y in min.1..max.1 {
-        for x in min.0..max.0 {
-            let c = match tiles.get(&(x, y)) {
-                Some(1) => 'x',
-                Some(2) => 'o',
-                Some(3) => '=',
-                Some(4) => '.',
-                Some(_) | None => ' ',
-            };
-            s.push(c);
+impl Arcade {
+    pub fn new(mem: Memory) -> Self {
+        Self {
+            mem,
+            ip: Some(0),
+            board: Board::default(),
+            output: Vec::with_capacity(3),
+            steps: 0,
+            recording: None,
         }
-        s.push('\n');
     }
-    println!("{}", s);
+
+    /// Records joystick inputs and the tile stream of all subsequent steps.
+    pub fn record(mut self) -> Self {
+        self.recording = Some(Recording::default());
+        self
+    }
+
+    pub fn recording(&self) -> Option<&Recording> {
+        self.recording.as_ref()
+    }
+
+    pub fn into_recording(self) -> Option<Recording> {
+        self.recording
+    }
+
+    /// Inserts quarters, i.e. `2` makes the game playable for free.
+    pub fn with_quarters(mut mem: Memory, quarters: i64) -> Self {
+        mem.write(0, quarters);
+        Self::new(mem)
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.ip.is_none()
+    }
+
+    /// Runs the game until the controller was asked for the next input or the game is over.
+    ///
+    /// Returns the joystick position that was fed into the game, if any.
+    pub fn step(&mut self, controller: &mut impl Controller) -> Result<Option<Joystick>> {
+        let mut joystick = None;
+        while let Some(ip) = self.ip {
+            let board = &self.board;
+            let output = &mut self.output;
+            self.ip = execute(
+                &mut self.mem,
+                ip,
+                || {
+                    let value = controller.joystick(board);
+                    joystick = Some(value);
+                    value.value()
+                },
+                |value| output.push(value),
+            )?;
+
+            if self.output.len() == 3 {
+                let pos = (self.output[0], self.output[1]);
+                self.board.update(pos, self.output[2])?;
+                if let Some(recording) = self.recording.as_mut() {
+                    recording.push_tile(pos, self.output[2]);
+                }
+                self.output.clear();
+            }
+            if let Some(joystick) = joystick {
+                if let Some(recording) = self.recording.as_mut() {
+                    recording.push_input(joystick);
+                }
+                self.steps += 1;
+                break;
+            }
+        }
+        Ok(joystick)
+    }
+
+    pub fn run(&mut self, controller: &mut impl Controller) -> Result<Outcome> {
+        while !self.is_over() {
+            self.step(controller)?;
+        }
+        Ok(self.outcome())
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        Outcome {
+            score: self.board.score(),
+            steps: self.steps,
+            blocks_remaining: self.board.num_blocks(),
+        }
+    }
+}
+
+fn print_tiles(board: &Board) {
+    let screen = board.tiles().render(&|tile: &Tile| match tile {
+        Tile::Wall => 'x',
+        Tile::Block => 'o',
+        Tile::Paddle => '=',
+        Tile::Ball => '.',
+        Tile::Empty => ' ',
+    });
+    println!("Score: {}\n{}", board.score(), screen);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_board() {
+        // draws a block, a paddle and the ball, sets the score and halts
+        let mem =
+            parse("104,1,104,2,104,2,104,0,104,3,104,3,104,2,104,1,104,4,104,-1,104,0,104,7,99");
+        let mut arcade = Arcade::new(mem);
+        let outcome = arcade.run(&mut FollowBall).unwrap();
+        assert_eq!(
+            outcome,
+            Outcome {
+                score: 7,
+                steps: 0,
+                blocks_remaining: 1
+            }
+        );
+        assert_eq!(arcade.board().get((1, 2)), Tile::Block);
+        assert_eq!(arcade.board().paddle(), Some((0, 3)));
+        assert_eq!(arcade.board().ball(), Some((2, 1)));
+        assert_eq!(FollowBall.joystick(arcade.board()), Joystick::Right);
+    }
+
+    #[test]
+    fn test_controller_input() {
+        // reads the joystick twice and echoes it as the score
+        let mem = parse("3,100,104,-1,104,0,4,100,3,100,104,-1,104,0,4,100,99");
+        let mut arcade = Arcade::new(mem);
+        let mut inputs = vec![Joystick::Right, Joystick::Left].into_iter();
+        let outcome = arcade.run(&mut |_: &Board| inputs.next().unwrap()).unwrap();
+        assert_eq!(outcome.score, -1);
+        assert_eq!(outcome.steps, 2);
+    }
 }
@@ -0,0 +1,304 @@
+//! Recording and playback of arcade sessions.
+//!
+//! A recording is a sequence of frames. Each frame holds the joystick input which started it
+//! (none for the very first frame) and the tiles the game drew until it asked for the next input.
+
+use super::{Arcade, Board, Coord, Joystick, Outcome, Tile};
+use crate::tui::Terminal;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::Duration;
+use termion::event::Key;
+
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
+
+const MAGIC: &[u8; 4] = b"D13R";
+const VERSION: u8 = 1;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frame {
+    pub input: Option<Joystick>,
+    pub tiles: Vec<(Coord, i64)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    frames: Vec<Frame>,
+}
+
+impl Recording {
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn inputs(&self) -> impl Iterator<Item = Joystick> + '_ {
+        self.frames.iter().filter_map(|frame| frame.input)
+    }
+
+    pub(super) fn push_tile(&mut self, pos: Coord, value: i64) {
+        if self.frames.is_empty() {
+            self.frames.push(Frame::default());
+        }
+        let frame = self.frames.last_mut().unwrap();
+        frame.tiles.push((pos, value));
+    }
+
+    pub(super) fn push_input(&mut self, input: Joystick) {
+        self.frames.push(Frame {
+            input: Some(input),
+            tiles: Vec::new(),
+        });
+    }
+
+    /// Encodes the recording as magic, version and frames of zigzag varints.
+    pub fn write_to(&self, mut w: impl Write) -> Result<()> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(MAGIC);
+        buf.push(VERSION);
+        write_varint(&mut buf, self.frames.len() as u64);
+        for frame in &self.frames {
+            buf.push(match frame.input {
+                None => 0,
+                Some(Joystick::Left) => 1,
+                Some(Joystick::Neutral) => 2,
+                Some(Joystick::Right) => 3,
+            });
+            write_varint(&mut buf, frame.tiles.len() as u64);
+            for &((x, y), value) in &frame.tiles {
+                for &n in [x, y, value].iter() {
+                    write_varint(&mut buf, zigzag(n));
+                }
+            }
+        }
+        w.write_all(&buf)?;
+        Ok(())
+    }
+
+    pub fn read_from(mut r: impl Read) -> Result<Self> {
+        let mut buf = Vec::new();
+        r.read_to_end(&mut buf)?;
+        if !buf.starts_with(MAGIC) {
+            return Err("not a day13 recording".into());
+        }
+        let mut bytes = buf[MAGIC.len()..].iter().copied();
+        match bytes.next() {
+            Some(VERSION) => {}
+            Some(version) => return Err(format!("unsupported version: {}", version).into()),
+            None => return Err("truncated recording".into()),
+        }
+
+        let num_frames = read_varint(&mut bytes)?;
+        let mut frames = Vec::new();
+        for _ in 0..num_frames {
+            let input = match bytes.next().ok_or("truncated recording")? {
+                0 => None,
+                1 => Some(Joystick::Left),
+                2 => Some(Joystick::Neutral),
+                3 => Some(Joystick::Right),
+                otherwise => return Err(format!("invalid input: {}", otherwise).into()),
+            };
+            let num_tiles = read_varint(&mut bytes)?;
+            let tiles: Result<_> = (0..num_tiles)
+                .map(|_| {
+                    let x = unzigzag(read_varint(&mut bytes)?);
+                    let y = unzigzag(read_varint(&mut bytes)?);
+                    let value = unzigzag(read_varint(&mut bytes)?);
+                    Ok(((x, y), value))
+                })
+                .collect();
+            frames.push(Frame {
+                input,
+                tiles: tiles?,
+            });
+        }
+        if bytes.next().is_some() {
+            return Err("trailing data after last frame".into());
+        }
+
+        Ok(Self { frames })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write_to(std::fs::File::create(path)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::read_from(std::fs::File::open(path)?)
+    }
+}
+
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag(n: u64) -> i64 {
+    (n >> 1) as i64 ^ -((n & 1) as i64)
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        buf.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> Result<u64> {
+    let mut n = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = bytes.next().ok_or("truncated recording")?;
+        n |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err("varint overflow".into())
+}
+
+/// Replays the recorded inputs on the given arcade and checks that the game draws exactly the
+/// recorded tiles.
+pub fn verify(arcade: Arcade, recording: &Recording) -> Result<Outcome> {
+    let mut arcade = arcade.record();
+    let mut inputs = recording.inputs();
+    let mut exhausted = false;
+    while !arcade.is_over() && !exhausted {
+        arcade.step(&mut |_: &Board| {
+            inputs.next().unwrap_or_else(|| {
+                exhausted = true;
+                Joystick::Neutral
+            })
+        })?;
+    }
+
+    let replayed = arcade.recording().map(Recording::frames).unwrap_or(&[]);
+    let expected = recording.frames();
+    if let Some(index) = replayed
+        .iter()
+        .zip(expected)
+        .position(|(replayed, expected)| replayed != expected)
+    {
+        return Err(format!("replay diverges at frame {}", index).into());
+    }
+    if exhausted {
+        return Err("game asks for more inputs than recorded".into());
+    }
+    if replayed.len() != expected.len() {
+        return Err(format!(
+            "game is over after {} frames, but {} frames were recorded",
+            replayed.len(),
+            expected.len()
+        )
+        .into());
+    }
+
+    Ok(arcade.outcome())
+}
+
+/// Slowest and fastest playback speed.
+const SPEEDS: (f64, f64) = (1.0 / 64.0, 64.0);
+
+/// Plays back a recording in the terminal.
+///
+/// `speed` scales the default frame rate of 50 frames per second and is clamped to `SPEEDS`.
+/// While playing, `space` pauses, `+`/`-` change the speed and `q` quits.
+pub fn play(recording: &Recording, speed: f64) -> Result<()> {
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err("speed must be positive".into());
+    }
+    let mut speed = speed.clamp(SPEEDS.0, SPEEDS.1);
+
+    let palette = |tile: &Tile| match tile {
+        Tile::Wall => '█',
+        Tile::Block => '▒',
+        Tile::Paddle => '▂',
+        Tile::Ball => '●',
+        Tile::Empty => ' ',
+    };
+
+    let mut terminal = Terminal::stdout()?;
+    let mut board = Board::default();
+    let mut frames = recording.frames().iter().enumerate();
+    let mut paused = false;
+    loop {
+        match terminal.poll_key() {
+            Some(Key::Char('q')) | Some(Key::Esc) | Some(Key::Ctrl('c')) => break,
+            Some(Key::Char(' ')) => paused = !paused,
+            Some(Key::Char('+')) => speed = (speed * 2.0).min(SPEEDS.1),
+            Some(Key::Char('-')) => speed = (speed / 2.0).max(SPEEDS.0),
+            _ => {}
+        }
+
+        if !paused {
+            let (index, frame) = match frames.next() {
+                Some(frame) => frame,
+                None => break,
+            };
+            for &(pos, value) in &frame.tiles {
+                board.update(pos, value)?;
+            }
+            let status = format!(
+                "Score: {}  Frame: {}/{}  Speed: {}x",
+                board.score(),
+                index + 1,
+                recording.frames().len(),
+                speed
+            );
+            terminal.draw(&status, board.tiles(), &palette)?;
+        }
+        std::thread::sleep(Duration::from_secs_f64(0.02 / speed));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day09::parse;
+
+    // reads the joystick twice and draws the ball at the column given by the input
+    const PROGRAM: &str = "104,0,104,0,104,1,3,100,4,100,104,0,104,4,3,100,4,100,104,1,104,4,99";
+
+    #[test]
+    fn test_encoding() {
+        let mut recording = Recording::default();
+        recording.push_tile((-1, 0), 123_456_789);
+        recording.push_input(Joystick::Left);
+        recording.push_tile((3, -7), 4);
+        recording.push_input(Joystick::Neutral);
+
+        let mut buf = Vec::new();
+        recording.write_to(&mut buf).unwrap();
+        assert_eq!(Recording::read_from(&buf[..]).unwrap(), recording);
+        assert!(Recording::read_from(&buf[..buf.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut inputs = vec![Joystick::Right, Joystick::Left].into_iter();
+        let mut arcade = Arcade::new(parse(PROGRAM)).record();
+        arcade.run(&mut |_: &Board| inputs.next().unwrap()).unwrap();
+        let recording = arcade.into_recording().unwrap();
+        assert_eq!(recording.frames().len(), 3);
+        assert_eq!(recording.frames()[1].tiles, vec![((1, 0), 4)]);
+
+        let outcome = verify(Arcade::new(parse(PROGRAM)), &recording).unwrap();
+        assert_eq!(outcome.steps, 2);
+
+        let mut tampered = recording.clone();
+        tampered.frames[1].input = Some(Joystick::Neutral);
+        assert!(verify(Arcade::new(parse(PROGRAM)), &tampered).is_err());
+
+        tampered.frames.pop();
+        assert!(verify(Arcade::new(parse(PROGRAM)), &tampered).is_err());
+    }
+
+    #[test]
+    fn test_play_speed() {
+        let recording = Recording::default();
+        for &speed in &[0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(play(&recording, speed).is_err());
+        }
+    }
+}
@@ -1,17 +1,23 @@
-use std::cmp::Ordering;
 use std::collections::HashMap;
 
-pub fn solve(input: &str) -> (usize, usize) {
-    let rules = parse(input);
-    let part1 = ore(&rules, 1);
-    let part2 = optimal_fuel(&rules);
-    (part1, part2)
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
+
+pub fn solve(input: &str) -> Result<(usize, usize)> {
+    let rules = parse(input)?;
+    let planner = Planner::new(&rules)?;
+    let part1 = planner.plan(&[(FUEL, 1)])?.raw[ORE];
+    let part2 = optimal_fuel(&rules)?;
+    Ok((part1, part2))
 }
 
+pub const ORE: &str = "ORE";
+pub const FUEL: &str = "FUEL";
+
 #[derive(Debug, Hash, PartialEq, Eq)]
-struct Term {
-    label: String,
-    mult: usize,
+pub struct Term {
+    pub label: String,
+    pub mult: usize,
 }
 
 impl Term {
@@ -23,72 +29,356 @@ impl Term {
     }
 }
 
-type Rules = HashMap<String, (usize, Vec<Term>)>;
+/// Reactions by the chemical they produce: (produced quantity, inputs).
+pub type Rules = HashMap<String, (usize, Vec<Term>)>;
 
-fn parse(input: &str) -> Rules {
-    let parse_term = |s: &str| {
-        let mut parts = s.trim().split(' ');
-        let quantity: usize = parts.next().unwrap().parse().unwrap();
-        let label = parts.next().unwrap();
-        Term::new(label, quantity)
+pub fn parse(input: &str) -> Result<Rules> {
+    let parse_term = |s: &str| -> Result<Term> {
+        let mut parts = s.split_whitespace();
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(quantity), Some(label), None) => Ok(Term::new(label, quantity.parse()?)),
+            _ => Err(format!("invalid term: {:?}", s).into()),
+        }
     };
 
-    input
-        .lines()
-        .map(|l| {
-            let mut parts = l.split("=>");
-            let lhs = parts.next().unwrap();
-            let rhs = parts.next().unwrap();
-            let target = parse_term(rhs);
-            let terms: Vec<_> = lhs.split(',').map(parse_term).collect();
-            (target.label, (target.mult, terms))
-        })
-        .collect()
+    let mut rules = Rules::new();
+    for l in input.lines().filter(|l| !l.trim().is_empty()) {
+        let mut parts = l.split("=>");
+        let (lhs, rhs) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(lhs), Some(rhs), None) => (lhs, rhs),
+            _ => return Err(format!("invalid reaction: {}", l).into()),
+        };
+        let target = parse_term(rhs)?;
+        let terms = lhs.split(',').map(parse_term).collect::<Result<Vec<_>>>()?;
+        if rules.contains_key(&target.label) {
+            return Err(format!("{} is produced by more than one reaction", target.label).into());
+        }
+        rules.insert(target.label, (target.mult, terms));
+    }
+    Ok(rules)
 }
 
-fn ore(rules: &Rules, fuel_amount: usize) -> usize {
-    let mut amounts = HashMap::new();
-    amounts.insert("FUEL".to_string(), fuel_amount as isize);
+/// What it takes to produce some chemicals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    /// Number of times each reaction fires, keyed by the chemical it produces.
+    pub fires: HashMap<String, usize>,
+    /// Chemicals produced but not consumed.
+    pub surplus: HashMap<String, usize>,
+    /// Consumed raw resources.
+    pub raw: HashMap<String, usize>,
+    /// Chemicals taken from the stock held before production.
+    pub used_stock: HashMap<String, usize>,
+    /// Firing reactions such that every one comes after the reactions producing its inputs.
+    pub order: Vec<String>,
+    /// Longest chain of firing reactions to a target, starting with a raw resource or a chemical
+    /// from the stock.
+    pub critical_path: Vec<String>,
+}
+
+/// Plans production over the validated reaction graph.
+#[derive(Debug)]
+pub struct Planner<'a> {
+    rules: &'a Rules,
+    resources: Vec<&'a str>,
+    /// Produced chemicals, every one after all chemicals it is consumed by.
+    order: Vec<&'a str>,
+}
+
+impl<'a> Planner<'a> {
+    /// Plans production from ORE.
+    pub fn new(rules: &'a Rules) -> Result<Self> {
+        Self::with_resources(rules, &[ORE])
+    }
+
+    /// Checks that the raw resources are only sources, every other chemical has a reaction, all
+    /// quantities are positive, and the reactions do not form a cycle.
+    pub fn with_resources(rules: &'a Rules, resources: &[&'a str]) -> Result<Self> {
+        if let Some(resource) = resources.iter().find(|&&r| rules.contains_key(r)) {
+            return Err(format!("{} must not be produced by a reaction", resource).into());
+        }
+        let mut consumers: HashMap<&str, usize> = rules.keys().map(|l| (l.as_str(), 0)).collect();
+        for (label, (mult, terms)) in rules {
+            if *mult == 0 {
+                return Err(format!("{} is produced in a quantity of 0", label).into());
+            }
+            for term in terms {
+                if term.mult == 0 {
+                    return Err(
+                        format!("{} consumes {} in a quantity of 0", label, term.label).into(),
+                    );
+                }
+                if term.label == *label {
+                    return Err(format!("{} is consumed by its own reaction", label).into());
+                }
+                match consumers.get_mut(term.label.as_str()) {
+                    Some(count) => *count += 1,
+                    None if resources.contains(&term.label.as_str()) => {}
+                    None => {
+                        return Err(format!("{} is not produced by any reaction", term.label).into())
+                    }
+                }
+            }
+        }
+
+        // Kahn's algorithm starting from the chemicals nothing consumes
+        let mut ready: Vec<&str> = consumers
+            .iter()
+            .filter(|&(_, &count)| count == 0)
+            .map(|(&label, _)| label)
+            .collect();
+        ready.sort_unstable();
+        let mut order = Vec::with_capacity(rules.len());
+        while let Some(label) = ready.pop() {
+            order.push(label);
+            for term in &rules[label].1 {
+                if let Some(count) = consumers.get_mut(term.label.as_str()) {
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.push(&term.label);
+                    }
+                }
+            }
+        }
+        if order.len() != rules.len() {
+            let mut cycle: Vec<&str> = consumers
+                .into_iter()
+                .filter(|&(_, count)| count > 0)
+                .map(|(label, _)| label)
+                .collect();
+            cycle.sort_unstable();
+            return Err(format!("reactions form a cycle among {}", cycle.join(", ")).into());
+        }
+
+        Ok(Self {
+            rules,
+            resources: resources.to_vec(),
+            order,
+        })
+    }
+
+    pub fn rules(&self) -> &Rules {
+        self.rules
+    }
+
+    pub fn resources(&self) -> &[&'a str] {
+        &self.resources
+    }
 
-    while let Some((label, amount)) = amounts
-        .iter()
-        .find(|&(label, &amount)| label != "ORE" && amount > 0)
-        .map(|(label, &amount)| (label.clone(), amount))
-    {
-        let (rule_mult, terms) = rules.get(&label).unwrap();
-        let rule_mult = *rule_mult as isize;
-        let mult = (rule_mult + amount - 1) / rule_mult;
-        amounts.insert(label, amount - mult * rule_mult);
-        for term in terms {
-            let value = amounts.entry(term.label.clone()).or_insert(0);
-            *value += mult * term.mult as isize;
+    fn check_chemical(&self, label: &str) -> Result<()> {
+        if self.resources.contains(&label) || self.rules.contains_key(label) {
+            Ok(())
+        } else {
+            Err(format!("unknown chemical: {}", label).into())
+        }
+    }
+
+    /// Plans the production of the given amounts of chemicals from the raw resources.
+    pub fn plan(&self, targets: &[(&str, usize)]) -> Result<Plan> {
+        self.plan_with_stock(targets, &HashMap::new())
+    }
+
+    /// Plans the production taking chemicals from the stock before producing them.
+    ///
+    /// Since the total demand of a chemical is known before its inputs are considered, using as
+    /// much of the stock as possible never costs more of anything.
+    pub fn plan_with_stock(
+        &self,
+        targets: &[(&str, usize)],
+        stock: &HashMap<String, usize>,
+    ) -> Result<Plan> {
+        self.try_plan(targets, stock)?
+            .ok_or_else(|| "quantities of the plan overflow".into())
+    }
+
+    /// Plans the production, or returns `None` if a quantity overflows.
+    fn try_plan(
+        &self,
+        targets: &[(&str, usize)],
+        stock: &HashMap<String, usize>,
+    ) -> Result<Option<Plan>> {
+        let mut needed: HashMap<&str, usize> = HashMap::new();
+        for &(label, amount) in targets {
+            self.check_chemical(label)?;
+            if add(&mut needed, label, amount).is_none() {
+                return Ok(None);
+            }
+        }
+
+        let mut used_stock = HashMap::new();
+        let mut take_stock = |label: &str, amount: usize| {
+            let held = stock.get(label).copied().unwrap_or(0).min(amount);
+            if held > 0 {
+                used_stock.insert(label.to_string(), held);
+            }
+            amount - held
+        };
+
+        let mut fires = HashMap::new();
+        let mut surplus = HashMap::new();
+        for &label in &self.order {
+            let amount = take_stock(label, needed.get(label).copied().unwrap_or(0));
+            if amount == 0 {
+                continue;
+            }
+            let (mult, terms) = &self.rules[label];
+            let times = amount.div_ceil(*mult);
+            fires.insert(label.to_string(), times);
+            let produced = match times.checked_mul(*mult) {
+                Some(produced) => produced,
+                None => return Ok(None),
+            };
+            if produced > amount {
+                surplus.insert(label.to_string(), produced - amount);
+            }
+            for term in terms {
+                let amount = times.checked_mul(term.mult);
+                if amount
+                    .and_then(|a| add(&mut needed, &term.label, a))
+                    .is_none()
+                {
+                    return Ok(None);
+                }
+            }
+        }
+        let raw: HashMap<String, usize> = needed
+            .into_iter()
+            .filter(|&(label, _)| !self.rules.contains_key(label))
+            .map(|(label, amount)| (label.to_string(), take_stock(label, amount)))
+            .collect();
+
+        let order: Vec<String> = self
+            .order
+            .iter()
+            .rev()
+            .filter(|&&label| fires.contains_key(label))
+            .map(|&label| label.to_string())
+            .collect();
+
+        // number of reactions on the longest chain ending in each chemical
+        let mut depth: HashMap<&str, usize> = HashMap::new();
+        for label in &order {
+            let inputs = &self.rules[label].1;
+            let max = inputs
+                .iter()
+                .map(|t| depth.get(t.label.as_str()).copied().unwrap_or(0));
+            depth.insert(label, max.max().unwrap_or(0) + 1);
+        }
+        let depth_of = |label: &str| depth.get(label).copied().unwrap_or(0);
+        let mut critical_path = Vec::new();
+        let mut current = targets
+            .iter()
+            .map(|&(label, _)| label)
+            .max_by_key(|&label| depth_of(label));
+        while let Some(label) = current {
+            critical_path.push(label.to_string());
+            // chemicals taken from the stock end the path like raw resources
+            if !fires.contains_key(label) {
+                break;
+            }
+            current = self.rules[label]
+                .1
+                .iter()
+                .map(|t| t.label.as_str())
+                .max_by_key(|&label| depth_of(label));
+        }
+        critical_path.reverse();
+
+        Ok(Some(Plan {
+            fires,
+            surplus,
+            raw,
+            used_stock,
+            order,
+            critical_path,
+        }))
+    }
+
+    /// Largest number of bundles which can be produced within the budgets of the raw resources,
+    /// where a bundle consists of the given weighted amounts of the targets.
+    ///
+    /// Resources without a budget are not available; the stock is used up first.
+    pub fn max_production(
+        &self,
+        weighted_targets: &[(&str, usize)],
+        budget: &HashMap<String, usize>,
+        stock: &HashMap<String, usize>,
+    ) -> Result<usize> {
+        if weighted_targets.iter().all(|&(_, weight)| weight == 0) {
+            return Err("no target with a positive weight".into());
+        }
+        // without any raw resource for a bundle, nothing limits the production
+        if self
+            .plan(weighted_targets)?
+            .raw
+            .values()
+            .all(|&amount| amount == 0)
+        {
+            return Err("production is unbounded".into());
+        }
+
+        // amounts which overflow are more than any budget
+        let is_feasible = |bundles: usize| -> Result<bool> {
+            let targets: Option<Vec<(&str, usize)>> = weighted_targets
+                .iter()
+                .map(|&(label, weight)| Some((label, weight.checked_mul(bundles)?)))
+                .collect();
+            let plan = match targets {
+                Some(targets) => self.try_plan(&targets, stock)?,
+                None => None,
+            };
+            Ok(plan.is_some_and(|plan| {
+                plan.raw
+                    .iter()
+                    .all(|(label, &amount)| amount <= budget.get(label).copied().unwrap_or(0))
+            }))
+        };
+
+        // double an upper bound until it is infeasible, then bisect
+        let mut min = 0;
+        let mut max = 1;
+        while is_feasible(max)? {
+            if max == usize::MAX {
+                return Ok(max);
+            }
+            min = max;
+            max = max.saturating_mul(2);
+        }
+        while min + 1 < max {
+            let mid = min + (max - min) / 2;
+            if is_feasible(mid)? {
+                min = mid;
+            } else {
+                max = mid;
+            }
         }
+        Ok(min)
     }
+}
 
-    *amounts.get("ORE").unwrap() as usize
+/// Adds to the needed amount of the chemical, or returns `None` on overflow.
+fn add<'b>(needed: &mut HashMap<&'b str, usize>, label: &'b str, amount: usize) -> Option<()> {
+    let total = needed.entry(label).or_insert(0);
+    *total = total.checked_add(amount)?;
+    Some(())
 }
 
-fn optimal_fuel(rules: &Rules) -> usize {
+fn optimal_fuel(rules: &Rules) -> Result<usize> {
     const MAX_ORE: usize = 1_000_000_000_000;
-    let mut min = 1;
-    let mut max = MAX_ORE;
-    // binary search for the optimal amount of fuel
-    while min + 1 < max {
-        let mid = (min + max) / 2;
-        let ore = ore(rules, mid);
-        match ore.cmp(&MAX_ORE) {
-            Ordering::Less => min = mid,
-            Ordering::Greater => max = mid,
-            Ordering::Equal => return mid,
-        }
-    }
-    min
+    let budget = vec![(ORE.to_string(), MAX_ORE)].into_iter().collect();
+    let planner = Planner::new(rules)?;
+    planner.max_production(&[(FUEL, 1)], &budget, &HashMap::new())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn ore(rules: &Rules, fuel_amount: usize) -> usize {
+        let plan = Planner::new(rules).unwrap().plan(&[(FUEL, fuel_amount)]);
+        plan.unwrap().raw[ORE]
+    }
+
     #[test]
     fn test_ore() {
         let rules = parse(
@@ -98,7 +388,8 @@ mod tests {
 7 A, 1 C => 1 D
 7 A, 1 D => 1 E
 7 A, 1 E => 1 FUEL"#,
-        );
+        )
+        .unwrap();
         assert_eq!(ore(&rules, 1), 31);
 
         let rules = parse(
@@ -109,7 +400,8 @@ mod tests {
 5 B, 7 C => 1 BC
 4 C, 1 A => 1 CA
 2 AB, 3 BC, 4 CA => 1 FUEL"#,
-        );
+        )
+        .unwrap();
         assert_eq!(ore(&rules, 1), 165);
 
         let rules = parse(
@@ -122,7 +414,8 @@ mod tests {
 7 DCFZ, 7 PSHF => 2 XJWVT
 165 ORE => 2 GPVTF
 3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT"#,
-        );
+        )
+        .unwrap();
         assert_eq!(ore(&rules, 1), 13312);
 
         let rules = parse(
@@ -138,7 +431,8 @@ mod tests {
 1 NVRVD => 8 CXFTF
 1 VJHF, 6 MNCFX => 4 RFSQX
 176 ORE => 6 VJHF"#,
-        );
+        )
+        .unwrap();
         assert_eq!(ore(&rules, 1), 180697);
 
         let rules = parse(
@@ -159,7 +453,8 @@ mod tests {
 121 ORE => 7 VRPVC
 7 XCVML => 6 RJRHP
 5 BHXH, 4 VRPVC => 5 LTCX"#,
-        );
+        )
+        .unwrap();
         assert_eq!(ore(&rules, 1), 2210736);
     }
 
@@ -175,8 +470,9 @@ mod tests {
 7 DCFZ, 7 PSHF => 2 XJWVT
 165 ORE => 2 GPVTF
 3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT"#,
-        );
-        assert_eq!(optimal_fuel(&rules), 82892753);
+        )
+        .unwrap();
+        assert_eq!(optimal_fuel(&rules).unwrap(), 82892753);
 
         let rules = parse(
             r#"2 VPVL, 7 FWMGM, 2 CXFTF, 11 MNCFX => 1 STKFG
@@ -191,8 +487,9 @@ mod tests {
 1 NVRVD => 8 CXFTF
 1 VJHF, 6 MNCFX => 4 RFSQX
 176 ORE => 6 VJHF"#,
-        );
-        assert_eq!(optimal_fuel(&rules), 5586022);
+        )
+        .unwrap();
+        assert_eq!(optimal_fuel(&rules).unwrap(), 5586022);
 
         let rules = parse(
             r#"171 ORE => 8 CNZTR
@@ -212,7 +509,142 @@ mod tests {
 121 ORE => 7 VRPVC
 7 XCVML => 6 RJRHP
 5 BHXH, 4 VRPVC => 5 LTCX"#,
+        )
+        .unwrap();
+        assert_eq!(optimal_fuel(&rules).unwrap(), 460664);
+
+        // fuel without any cost cannot be bounded
+        let mut rules = Rules::new();
+        rules.insert(FUEL.to_string(), (1, Vec::new()));
+        assert_eq!(
+            optimal_fuel(&rules).unwrap_err().to_string(),
+            "production is unbounded"
         );
-        assert_eq!(optimal_fuel(&rules), 460664);
+    }
+
+    #[test]
+    fn test_plan() {
+        let rules = parse(
+            r#"10 ORE => 10 A
+1 ORE => 1 B
+7 A, 1 B => 1 C
+7 A, 1 C => 1 D
+7 A, 1 D => 1 E
+7 A, 1 E => 1 FUEL"#,
+        )
+        .unwrap();
+        let plan = Planner::new(&rules).unwrap().plan(&[(FUEL, 1)]).unwrap();
+        assert_eq!(plan.fires["A"], 3);
+        assert_eq!(plan.fires["E"], 1);
+        assert_eq!(plan.surplus.get("A"), Some(&2));
+        assert_eq!(plan.surplus.len(), 1);
+        assert_eq!(plan.raw[ORE], 31);
+        assert_eq!(plan.order.last().map(String::as_str), Some(FUEL));
+        let position = |label: &str| plan.order.iter().position(|l| l == label).unwrap();
+        assert!(position("A") < position("C") && position("B") < position("C"));
+        assert_eq!(plan.critical_path, vec!["ORE", "B", "C", "D", "E", "FUEL"]);
+    }
+
+    #[test]
+    fn test_validation() {
+        let err = |input| {
+            let rules = parse(input)?;
+            Planner::new(&rules).map(|_| ())
+        };
+        let message = |input| err(input).unwrap_err().to_string();
+        assert_eq!(
+            message("1 ORE => 1 A\n2 ORE => 1 A"),
+            "A is produced by more than one reaction"
+        );
+        assert_eq!(
+            message("1 A => 1 B\n1 B => 1 A\n1 A => 1 FUEL"),
+            "reactions form a cycle among A, B"
+        );
+        assert_eq!(
+            message("1 A => 1 ORE"),
+            "ORE must not be produced by a reaction"
+        );
+        assert_eq!(
+            message("1 X => 1 FUEL"),
+            "X is not produced by any reaction"
+        );
+        assert_eq!(message("1 ORE => x FUEL"), "invalid digit found in string");
+        assert_eq!(
+            message("1 ORE => 0 A\n1 A => 1 FUEL"),
+            "A is produced in a quantity of 0"
+        );
+        assert_eq!(
+            message("0 ORE => 1 A\n1 A => 1 FUEL"),
+            "A consumes ORE in a quantity of 0"
+        );
+        assert!(solve("1 ORE => 0 A\n1 A => 1 FUEL").is_err());
+        assert!(err("1 ORE => 1 FUEL").is_ok());
+    }
+
+    #[test]
+    fn test_resources_and_stock() {
+        let rules = parse(
+            r#"10 ORE => 10 A
+1 ORE => 1 B
+7 A, 1 B => 1 C
+7 A, 1 C => 1 D
+7 A, 1 D => 1 E
+7 A, 1 E => 1 FUEL"#,
+        )
+        .unwrap();
+        let planner = Planner::new(&rules).unwrap();
+        let stock: HashMap<String, usize> = vec![("E".to_string(), 1), ("A".to_string(), 5)]
+            .into_iter()
+            .collect();
+        let plan = planner.plan_with_stock(&[(FUEL, 1)], &stock).unwrap();
+        assert_eq!(plan.raw[ORE], 10);
+        assert_eq!(plan.used_stock["E"], 1);
+        assert_eq!(plan.used_stock["A"], 5);
+        assert_eq!(plan.critical_path, vec!["ORE", "A", "FUEL"]);
+
+        let budget: HashMap<String, usize> = vec![(ORE.to_string(), 100)].into_iter().collect();
+        let fuel = planner.max_production(&[(FUEL, 1)], &budget, &HashMap::new());
+        assert_eq!(fuel.unwrap(), 3);
+        let fuel = planner.max_production(&[(FUEL, 1)], &budget, &stock);
+        assert_eq!(fuel.unwrap(), 4);
+
+        // B is mined from a separate deposit, D and E are wanted in a 1:2 ratio
+        let rules =
+            parse("10 ORE => 10 A\n7 A, 1 B => 1 C\n7 A, 1 C => 1 D\n7 A, 1 D => 1 E").unwrap();
+        assert!(Planner::new(&rules).is_err());
+        let planner = Planner::with_resources(&rules, &[ORE, "B"]).unwrap();
+        let budget: HashMap<String, usize> = vec![(ORE.to_string(), 1000), ("B".to_string(), 6)]
+            .into_iter()
+            .collect();
+        let bundles = planner.max_production(&[("D", 1), ("E", 2)], &budget, &HashMap::new());
+        assert_eq!(bundles.unwrap(), 2);
+        assert!(Planner::with_resources(&rules, &[ORE, "B", "C"]).is_err());
+
+        // B comes from the stock, so the reactions producing it do not fire
+        let rules = parse("1 ORE => 1 A\n1 A => 1 B\n1 ORE, 1 B => 1 FUEL").unwrap();
+        let planner = Planner::new(&rules).unwrap();
+        let stock = vec![("B".to_string(), 1)].into_iter().collect();
+        let plan = planner.plan_with_stock(&[(FUEL, 1)], &stock).unwrap();
+        assert_eq!(plan.critical_path, vec!["B", "FUEL"]);
+        let plan = planner.plan(&[(FUEL, 1)]).unwrap();
+        assert_eq!(plan.critical_path, vec!["ORE", "A", "B", "FUEL"]);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let rules = parse("1 ORE => 1 FUEL").unwrap();
+        let planner = Planner::new(&rules).unwrap();
+        let budget: HashMap<String, usize> =
+            vec![(ORE.to_string(), usize::MAX)].into_iter().collect();
+        let fuel = planner.max_production(&[(FUEL, 1)], &budget, &HashMap::new());
+        assert_eq!(fuel.unwrap(), usize::MAX);
+        let fuel = planner.max_production(&[(FUEL, 3)], &budget, &HashMap::new());
+        assert_eq!(fuel.unwrap(), usize::MAX / 3);
+
+        let rules = parse("1000 ORE => 1 A\n1000 A => 1 FUEL").unwrap();
+        let planner = Planner::new(&rules).unwrap();
+        assert!(planner.plan(&[(FUEL, usize::MAX / 10)]).is_err());
+        let fuel = planner.max_production(&[(FUEL, 1)], &budget, &HashMap::new());
+        assert_eq!(fuel.unwrap(), usize::MAX / 1_000_000);
     }
 }
@@ -1,192 +1,503 @@
 use crate::day09::{execute, parse, Memory};
-use rand::{thread_rng, Rng};
+use crate::tui::{Canvas, Terminal};
 
-use std::cell::RefCell;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::time::Duration;
+use termion::event::Key;
 
 type Error = Box<dyn std::error::Error>;
 type Result<T> = std::result::Result<T, Error>;
 type Coord = (i64, i64);
 
+/// Upper bound of executed instructions while exploring; the maze is explored in a few hundred
+/// thousand.
+pub const MAX_VM_STEPS: usize = 100_000_000;
+
+const ORIGIN: Coord = (0, 0);
+
 pub fn solve(input: &str) -> Result<(usize, usize)> {
     let mem = parse(input);
 
-    let tiles = collect_tiles(mem)?;
-    print_tiles(&tiles, (0, 0));
+    let map = explore(&mut Droid::new(mem, MAX_VM_STEPS))?;
+    print_map(&map, ORIGIN);
 
-    let oxygen_system_pos = tiles
+    let oxygen_system_pos = map
         .iter()
-        .find_map(|(&pos, &(id, _))| if id == 2 { Some(pos) } else { None })
-        .ok_or_else(|| "no oxygen system found")?;
-    let part1 = distance((0, 0), tiles.clone(), |pos| pos == oxygen_system_pos)
-        .ok_or_else(|| "no path to oxygen system found")?;
-    let part2 =
-        distance(oxygen_system_pos, tiles, |_| false).ok_or_else(|| "invalid start position")?;
+        .find_map(|(pos, &tile)| {
+            if tile == Tile::Oxygen {
+                Some(pos)
+            } else {
+                None
+            }
+        })
+        .ok_or("no oxygen system found")?;
+    let part1 = *distances(&map, ORIGIN)
+        .get(&oxygen_system_pos)
+        .ok_or("no path to oxygen system found")?;
+    let mut flood = Flood::from_oxygen_systems(map);
+    flood.by_ref().for_each(drop);
+    let part2 = flood.minute();
 
     Ok((part1, part2))
 }
 
-fn walk(pos: Coord, cmd: i64) -> Coord {
-    match cmd {
-        1 => (pos.0, pos.1 + 1),
-        2 => (pos.0, pos.1 - 1),
-        3 => (pos.0 - 1, pos.1),
-        4 => (pos.0 + 1, pos.1),
-        _ => panic!("unexpected move command"),
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tile {
+    Wall,
+    Open,
+    Oxygen,
+}
+
+impl TryFrom<i64> for Tile {
+    type Error = Error;
+    fn try_from(value: i64) -> Result<Self> {
+        match value {
+            0 => Ok(Tile::Wall),
+            1 => Ok(Tile::Open),
+            2 => Ok(Tile::Oxygen),
+            otherwise => Err(format!("invalid status code: {}", otherwise).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move {
+    North,
+    South,
+    West,
+    East,
+}
+
+impl Move {
+    pub const ALL: [Move; 4] = [Move::North, Move::South, Move::West, Move::East];
+
+    pub fn value(self) -> i64 {
+        match self {
+            Move::North => 1,
+            Move::South => 2,
+            Move::West => 3,
+            Move::East => 4,
+        }
+    }
+
+    pub fn opposite(self) -> Move {
+        match self {
+            Move::North => Move::South,
+            Move::South => Move::North,
+            Move::West => Move::East,
+            Move::East => Move::West,
+        }
+    }
+
+    /// North is up on the screen, i.e. towards smaller y.
+    pub fn apply(self, (x, y): Coord) -> Coord {
+        match self {
+            Move::North => (x, y - 1),
+            Move::South => (x, y + 1),
+            Move::West => (x - 1, y),
+            Move::East => (x + 1, y),
+        }
     }
 }
 
 fn neighbors(pos: Coord) -> impl Iterator<Item = Coord> {
-    let mut cmd = 0;
-    std::iter::from_fn(move || {
-        if cmd < 4 {
-            cmd += 1;
-            Some(walk(pos, cmd))
-        } else {
-            None
+    Move::ALL.iter().map(move |m| m.apply(pos))
+}
+
+/// Something that moves the repair droid and reports what it ran into.
+pub trait Remote {
+    fn send(&mut self, cmd: Move) -> Result<Tile>;
+}
+
+/// Repair droid controlled by the Intcode program.
+pub struct Droid {
+    mem: Memory,
+    ip: Option<usize>,
+    steps: usize,
+    max_steps: usize,
+}
+
+impl Droid {
+    /// The droid fails once the program executed more than `max_steps` instructions.
+    pub fn new(mem: Memory, max_steps: usize) -> Self {
+        Self {
+            mem,
+            ip: Some(0),
+            steps: 0,
+            max_steps,
+        }
+    }
+
+    /// Number of executed instructions.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+}
+
+impl Remote for Droid {
+    fn send(&mut self, cmd: Move) -> Result<Tile> {
+        let mut input = Some(cmd.value());
+        let mut output = None;
+        let mut starved = false;
+        while output.is_none() {
+            let ip = self.ip.ok_or("droid program halted")?;
+            if self.steps >= self.max_steps {
+                return Err(format!("droid exceeded {} VM steps", self.max_steps).into());
+            }
+            self.ip = execute(
+                &mut self.mem,
+                ip,
+                || {
+                    input.take().unwrap_or_else(|| {
+                        starved = true;
+                        0
+                    })
+                },
+                |value| output = Some(value),
+            )?;
+            self.steps += 1;
+            if starved {
+                return Err("droid asked for a second move before reporting".into());
+            }
         }
-    })
-}
-
-type Tiles = HashMap<Coord, (i64, bool)>;
-
-#[derive(Debug, Default)]
-struct State {
-    pos: Coord,
-    move_cmd: i64,
-    tiles: Tiles, // mapping tile pos -> (id, visited)
-}
-
-// We are lazy so we just do a random walk, since we know that it
-// will converge to the solution eventually.
-fn collect_tiles(mut mem: Memory) -> Result<Tiles> {
-    let mut tiles: HashMap<Coord, _> = HashMap::new();
-    tiles.insert((0, 0), (1, false));
-    for pos in neighbors((0, 0)) {
-        tiles.insert(pos, (1, false));
-    }
-    let state = RefCell::new(State {
-        pos: (0, 0),
-        move_cmd: 1,
-        tiles,
-    });
-
-    let mut rng = thread_rng();
-    let mut cmds = Vec::with_capacity(4);
-    let mut guess_input = |state: &mut State| {
-        cmds.clear();
-        for (move_cmd, pos) in neighbors(state.pos)
-            .enumerate()
-            .map(|(i, pos)| ((i + 1) as i64, pos))
-        {
-            let &(id, visited) = state.tiles.get(&pos).unwrap_or(&(1, false));
-            if id != 0 {
-                if !visited {
-                    state.move_cmd = move_cmd;
-                    return move_cmd;
-                } else {
-                    cmds.push(move_cmd);
+        Tile::try_from(output.unwrap())
+    }
+}
+
+/// Maps the whole area by a depth-first search.
+///
+/// The droid tries every unknown neighbor and, once there is none left, steps back the way it
+/// came, so every open tile is entered at most once forwards and once backwards.
+pub fn explore(remote: &mut impl Remote) -> Result<Canvas<Tile>> {
+    let mut map = Canvas::new();
+    map.set(ORIGIN, Tile::Open);
+    let mut pos = ORIGIN;
+    let mut path: Vec<Move> = Vec::new();
+    loop {
+        let unknown = Move::ALL
+            .iter()
+            .copied()
+            .find(|m| map.get(m.apply(pos)).is_none());
+        match unknown {
+            Some(cmd) => {
+                let target = cmd.apply(pos);
+                let tile = remote.send(cmd)?;
+                map.set(target, tile);
+                if tile != Tile::Wall {
+                    pos = target;
+                    path.push(cmd);
+                }
+            }
+            None => {
+                let cmd = match path.pop() {
+                    Some(cmd) => cmd.opposite(),
+                    None => break,
+                };
+                if remote.send(cmd)? == Tile::Wall {
+                    return Err(format!("droid cannot step back from {:?}", pos).into());
                 }
+                pos = cmd.apply(pos);
             }
         }
-        state.move_cmd = cmds[rng.gen_range(0, cmds.len())];
-        state.move_cmd
-    };
+    }
+    Ok(map)
+}
 
-    let process_output = |value, state: &mut State| {
-        let (_, visited) = state.tiles.get_mut(&state.pos).unwrap();
-        *visited = true;
+fn print_map(map: &Canvas<Tile>, pos: Coord) {
+    let mut canvas: Canvas<char> = map
+        .iter()
+        .map(|(pos, tile)| {
+            let c = match tile {
+                Tile::Wall => 'x',
+                Tile::Oxygen => '.',
+                Tile::Open => ' ',
+            };
+            (pos, c)
+        })
+        .collect();
+    canvas.set(pos, 'o');
+    println!("{}\n", canvas.render(&|&c: &char| c));
+}
 
-        match value {
-            0 => {
-                let wall_pos = walk(state.pos, state.move_cmd);
-                state.tiles.insert(wall_pos, (0, true));
+/// Number of steps from `orig` to every reachable tile.
+fn distances(map: &Canvas<Tile>, orig: Coord) -> HashMap<Coord, usize> {
+    let mut dists = HashMap::new();
+    dists.insert(orig, 0);
+    let mut q = VecDeque::new();
+    q.push_back(orig);
+    while let Some(pos) = q.pop_front() {
+        let dist = dists[&pos];
+        for neighbor in neighbors(pos) {
+            let is_open = map.get(neighbor).is_some_and(|&tile| tile != Tile::Wall);
+            if is_open && !dists.contains_key(&neighbor) {
+                dists.insert(neighbor, dist + 1);
+                q.push_back(neighbor);
+            }
+        }
+    }
+    dists
+}
+
+/// Oxygen spreading from its sources to the adjacent open tiles every minute.
+#[derive(Debug, Clone)]
+pub struct Flood {
+    map: Canvas<Tile>,
+    filled: HashSet<Coord>,
+    frontier: Vec<Coord>,
+    minute: usize,
+}
+
+impl Flood {
+    /// Starts at minute 0 with the sources filled; sources on walls or off the map are ignored.
+    pub fn new(map: Canvas<Tile>, sources: impl IntoIterator<Item = Coord>) -> Self {
+        let mut flood = Self {
+            map,
+            filled: HashSet::new(),
+            frontier: Vec::new(),
+            minute: 0,
+        };
+        for pos in sources {
+            if flood.is_open(pos) && flood.filled.insert(pos) {
+                flood.frontier.push(pos);
             }
-            1 | 2 => {
-                state.pos = walk(state.pos, state.move_cmd);
-                let (id, _) = state.tiles.get_mut(&state.pos).unwrap();
-                *id = value;
-                for pos in neighbors(state.pos) {
-                    state.tiles.entry(pos).or_insert((value, false));
+        }
+        flood
+    }
+
+    /// Starts from every oxygen system on the map.
+    pub fn from_oxygen_systems(map: Canvas<Tile>) -> Self {
+        let sources: Vec<Coord> = map
+            .iter()
+            .filter(|&(_, &tile)| tile == Tile::Oxygen)
+            .map(|(pos, _)| pos)
+            .collect();
+        Self::new(map, sources)
+    }
+
+    fn is_open(&self, pos: Coord) -> bool {
+        self.map.get(pos).is_some_and(|&tile| tile != Tile::Wall)
+    }
+
+    /// Number of minutes in which the oxygen spread.
+    pub fn minute(&self) -> usize {
+        self.minute
+    }
+
+    /// Tiles filled in the last minute.
+    pub fn frontier(&self) -> &[Coord] {
+        &self.frontier
+    }
+
+    pub fn filled(&self) -> &HashSet<Coord> {
+        &self.filled
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.frontier.is_empty()
+    }
+
+    /// Puts up a wall, e.g. a sealed door; oxygen on the tile is lost.
+    pub fn add_wall(&mut self, pos: Coord) {
+        self.map.set(pos, Tile::Wall);
+        self.filled.remove(&pos);
+        self.frontier.retain(|&p| p != pos);
+    }
+
+    /// Lets the oxygen spread for a minute and returns the newly filled tiles.
+    pub fn spread(&mut self) -> &[Coord] {
+        let mut next = Vec::new();
+        for &pos in &self.frontier {
+            for neighbor in neighbors(pos) {
+                if self.is_open(neighbor) && !self.filled.contains(&neighbor) {
+                    self.filled.insert(neighbor);
+                    next.push(neighbor);
                 }
             }
-            _ => panic!("unexpected output"),
-        };
-    };
-
-    let mut ip = Some(0);
-    while let Some(next_ip) = ip {
-        ip = execute(
-            &mut mem,
-            next_ip,
-            || guess_input(&mut state.borrow_mut()),
-            |value| process_output(value, &mut state.borrow_mut()),
-        )?;
-        let all_visited = state
-            .borrow()
-            .tiles
-            .values()
-            .all(|(id, visited)| *id == 0 || *visited);
-        if all_visited {
-            break;
         }
+        if !next.is_empty() {
+            self.minute += 1;
+        }
+        self.frontier = next;
+        &self.frontier
     }
 
-    let tiles = state.into_inner().tiles;
-    Ok(tiles)
+    /// Renders walls, open tiles, filled tiles and the frontier.
+    pub fn frame(&self) -> Canvas<char> {
+        let mut frame: Canvas<char> = self
+            .map
+            .iter()
+            .map(|(pos, tile)| {
+                let c = match tile {
+                    Tile::Wall => '█',
+                    _ if self.filled.contains(&pos) => '░',
+                    _ => ' ',
+                };
+                (pos, c)
+            })
+            .collect();
+        for &pos in &self.frontier {
+            frame.set(pos, 'O');
+        }
+        frame
+    }
 }
 
-fn print_tiles(tiles: &Tiles, pos: Coord) {
-    use std::i64::{MAX, MIN};
-    let (min, max) = tiles
-        .keys()
-        .fold(((MAX, MAX), (MIN, MIN)), |(min, max), pos| {
-            let min = (min.0.min(pos.0), min.1.min(pos.1));
-            let max = (max.0.max(pos.0), max.1.max(pos.1));
-            (min, max)
-        });
-    let mut s = String::new();
-    for y in min.1..=max.1 {
-        for x in min.0..=max.0 {
-            if (x, y) == pos {
-                s.push('o');
-            } else {
-                let c = match tiles.get(&(x, y)).map(|v| v.0) {
-                    Some(0) => 'x',
-                    Some(2) => '.',
-                    Some(_) | None => ' ',
-                };
-                s.push(c);
-            }
+impl Iterator for Flood {
+    type Item = Vec<Coord>;
+
+    /// Yields the tiles filled in each further minute until the oxygen stops spreading.
+    fn next(&mut self) -> Option<Self::Item> {
+        let frontier = self.spread().to_vec();
+        if frontier.is_empty() {
+            None
+        } else {
+            Some(frontier)
         }
-        s.push('\n');
     }
-    println!("{}", s);
 }
 
-fn distance(orig: Coord, mut tiles: Tiles, stop_cond: impl Fn(Coord) -> bool) -> Option<usize> {
-    // reset visited tiles
-    for (_, visited) in tiles.values_mut() {
-        *visited = false;
+/// Draws the flood minute by minute in the terminal until it stops spreading or `q` is pressed.
+///
+/// Returns the number of minutes shown.
+pub fn watch(flood: &mut Flood, delay: Duration) -> Result<usize> {
+    let mut terminal = Terminal::stdout()?;
+    let palette = |&c: &char| c;
+    loop {
+        let status = format!(
+            "Minute: {}  Filled: {}  Frontier: {}",
+            flood.minute(),
+            flood.filled().len(),
+            flood.frontier().len()
+        );
+        terminal.draw(&status, &flood.frame(), &palette)?;
+        if let Some(Key::Char('q')) | Some(Key::Esc) | Some(Key::Ctrl('c')) = terminal.poll_key() {
+            break;
+        }
+        std::thread::sleep(delay);
+        if flood.spread().is_empty() {
+            break;
+        }
     }
+    Ok(flood.minute())
+}
 
-    let mut q = VecDeque::new();
-    q.push_back((orig, 0));
-    tiles.get_mut(&orig)?.1 = true;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Remote driving a droid through a map drawn with `#` walls and `O` for the oxygen system;
+    /// the droid starts at `D`.
+    struct Simulation {
+        tiles: HashMap<Coord, Tile>,
+        pos: Coord,
+        moves: usize,
+    }
 
-    let mut max_dist = 0;
-    while let Some((pos, dist)) = q.pop_front() {
-        if stop_cond(pos) {
-            return Some(dist);
+    impl Simulation {
+        fn new(map: &str) -> Self {
+            let mut tiles = HashMap::new();
+            let mut start = ORIGIN;
+            for (y, line) in map.lines().enumerate() {
+                for (x, c) in line.chars().enumerate() {
+                    let pos = (x as i64, y as i64);
+                    let tile = match c {
+                        '#' => Tile::Wall,
+                        'O' => Tile::Oxygen,
+                        _ => Tile::Open,
+                    };
+                    if c == 'D' {
+                        start = pos;
+                    }
+                    tiles.insert(pos, tile);
+                }
+            }
+            // shift the map such that the droid starts at the origin
+            let tiles = tiles
+                .into_iter()
+                .map(|((x, y), tile)| ((x - start.0, y - start.1), tile))
+                .collect();
+            Self {
+                tiles,
+                pos: ORIGIN,
+                moves: 0,
+            }
         }
-        max_dist = dist;
-        for neighbor in neighbors(pos) {
-            let (id, seen) = tiles.get_mut(&neighbor)?;
-            if *id != 0 && !*seen {
-                *seen = true;
-                q.push_back((neighbor, dist + 1));
+    }
+
+    impl Remote for Simulation {
+        fn send(&mut self, cmd: Move) -> Result<Tile> {
+            self.moves += 1;
+            let target = cmd.apply(self.pos);
+            let tile = *self.tiles.get(&target).ok_or("left the map")?;
+            if tile != Tile::Wall {
+                self.pos = target;
             }
+            Ok(tile)
         }
     }
-    Some(max_dist)
+
+    const MAZE: &str = "\
+#####
+#..##
+#.#..#
+#.O.#
+#####";
+
+    #[test]
+    fn test_explore() {
+        let mut sim = Simulation::new(&MAZE.replacen('.', "D", 1));
+        let map = explore(&mut sim).unwrap();
+        // the droid returns to the start after seeing every tile it can reach
+        assert_eq!(sim.pos, ORIGIN);
+        assert_eq!(map.values().filter(|&&t| t == Tile::Open).count(), 7);
+        assert_eq!(map.values().filter(|&&t| t == Tile::Wall).count(), 13);
+        assert_eq!(map.get((1, 2)), Some(&Tile::Oxygen));
+        assert_eq!(distances(&map, ORIGIN)[&(1, 2)], 3);
+        // every tile is probed once, and the droid steps back once from every tile it entered
+        assert_eq!(sim.moves, (map.len() - 1) + 7);
+    }
+
+    #[test]
+    fn test_droid_limits() {
+        // reads a move, outputs 1 forever
+        let mem = parse("3,100,104,1,1105,1,0");
+        let mut droid = Droid::new(mem.clone(), 100);
+        assert_eq!(droid.send(Move::North).unwrap(), Tile::Open);
+        assert_eq!(droid.steps(), 2);
+        assert!(explore(&mut droid).is_err());
+
+        // reads two moves before answering
+        let mem = parse("3,100,3,100,104,0,99");
+        assert!(Droid::new(mem, 100).send(Move::East).is_err());
+    }
+
+    #[test]
+    fn test_flood() {
+        let mut sim = Simulation::new(&MAZE.replacen('.', "D", 1));
+        let map = explore(&mut sim).unwrap();
+
+        let mut flood = Flood::from_oxygen_systems(map.clone());
+        assert_eq!(flood.frontier(), &[(1, 2)]);
+        let minutes: Vec<Vec<Coord>> = flood.by_ref().collect();
+        assert_eq!(minutes.len(), 4);
+        assert_eq!(minutes[0].len(), 2);
+        assert_eq!(minutes[3], vec![(1, 0)]);
+        assert_eq!(flood.minute(), 4);
+        assert_eq!(flood.filled().len(), 8);
+        assert!(flood.is_done());
+
+        // a second source next to the start saves a minute
+        let flood = Flood::new(map.clone(), vec![(1, 2), (1, 0), (-5, -5)]);
+        assert_eq!(flood.filled().len(), 2);
+        assert_eq!(flood.count(), 3);
+
+        // sealing the corridor keeps the oxygen away from the start
+        let mut flood = Flood::from_oxygen_systems(map);
+        flood.add_wall((0, 1));
+        flood.by_ref().for_each(drop);
+        assert!(!flood.filled().contains(&ORIGIN));
+        assert_eq!(flood.filled().len(), 5);
+        assert_eq!(flood.frame().get((0, 1)), Some(&'█'));
+    }
 }
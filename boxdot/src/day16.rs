@@ -1,13 +1,10 @@
-pub fn solve(input: &str) -> (i64, i64) {
-    let mut seq = parse(input);
-    let mut buf = vec![0; seq.len()];
-    for _ in 0..100 {
-        phase(&seq, &mut buf);
-        std::mem::swap(&mut seq, &mut buf);
-    }
-    let part1 = to_number(seq[0..8].iter());
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
 
-    (part1, part2(&parse(input)))
+pub fn solve(input: &str) -> Result<(i64, i64)> {
+    let signal = parse(input);
+    let part1 = Fft::new(signal.clone(), 1).run(100, 0, 8, Strategy::Auto)?;
+    Ok((to_number(part1.iter()), part2(&signal)?))
 }
 
 fn parse(input: &str) -> Vec<u8> {
@@ -17,58 +14,202 @@ fn parse(input: &str) -> Vec<u8> {
         .collect()
 }
 
-fn pattern_ones(n: usize) -> impl Iterator<Item = usize> {
-    (0..)
-        .map(move |i| n * (1 + 4 * i)..n * (2 + 4 * i))
-        .flatten()
+/// How to compute the phases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Picks the cheapest applicable strategy.
+    Auto,
+    /// Sums the blocks of the pattern with prefix sums; works for any offset and takes
+    /// O(n log n) per phase for the n digits from the offset on.
+    PrefixSums,
+    /// In the second half, every digit becomes the sum of all digits from it on, so that a phase
+    /// is a single suffix sum.
+    SuffixSums,
+    /// In the second half, digit i after p phases is the sum of the input digits j >= i weighted
+    /// by C(p - 1 + j - i, j - i), which is computed modulo 10 with Lucas' theorem, so that the
+    /// cost depends on the number of requested digits instead of the number of phases.
+    Binomial,
+}
+
+/// Flawed Frequency Transmission of a signal repeated a number of times.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fft {
+    signal: Vec<u8>,
+    repeat: usize,
 }
 
-fn pattern_minus_ones(n: usize) -> impl Iterator<Item = usize> {
-    (0..)
-        .map(move |i| n * (3 + 4 * i)..n * (4 + 4 * i))
-        .flatten()
+impl Fft {
+    pub fn new(signal: Vec<u8>, repeat: usize) -> Self {
+        Self { signal, repeat }
+    }
+
+    pub fn len(&self) -> usize {
+        self.signal.len() * self.repeat
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Digits from the offset to the end of the repeated signal.
+    fn tail(&self, offset: usize) -> Vec<u8> {
+        (offset..self.len())
+            .map(|i| self.signal[i % self.signal.len()])
+            .collect()
+    }
+
+    /// Whether all digits from the offset on only depend on the digits after them.
+    fn is_second_half(&self, offset: usize) -> bool {
+        2 * offset + 1 >= self.len()
+    }
+
+    /// Resolves `Strategy::Auto` by comparing the number of digit operations.
+    pub fn choose(&self, phases: usize, offset: usize, count: usize) -> Strategy {
+        if !self.is_second_half(offset) {
+            Strategy::PrefixSums
+        } else if count < phases {
+            Strategy::Binomial
+        } else {
+            Strategy::SuffixSums
+        }
+    }
+
+    /// Returns `count` digits from the offset on after the given number of phases.
+    pub fn run(
+        &self,
+        phases: usize,
+        offset: usize,
+        count: usize,
+        strategy: Strategy,
+    ) -> Result<Vec<u8>> {
+        if offset + count > self.len() {
+            return Err(format!(
+                "cannot take {} digits at offset {} of a signal of length {}",
+                count,
+                offset,
+                self.len()
+            )
+            .into());
+        }
+        let strategy = match strategy {
+            Strategy::Auto => self.choose(phases, offset, count),
+            strategy => strategy,
+        };
+        if strategy != Strategy::PrefixSums && !self.is_second_half(offset) {
+            return Err(format!(
+                "{:?} needs an offset in the second half, but {} < {}",
+                strategy,
+                offset,
+                self.len() / 2
+            )
+            .into());
+        }
+
+        let mut digits = self.tail(offset);
+        match strategy {
+            Strategy::PrefixSums => {
+                let mut buf = vec![0; digits.len()];
+                for _ in 0..phases {
+                    phase(&digits, offset, &mut buf);
+                    std::mem::swap(&mut digits, &mut buf);
+                }
+            }
+            Strategy::SuffixSums => {
+                for _ in 0..phases {
+                    let mut sum = 0;
+                    for digit in digits.iter_mut().rev() {
+                        sum = (sum + *digit) % 10;
+                        *digit = sum;
+                    }
+                }
+            }
+            Strategy::Binomial => {
+                if phases > 0 {
+                    let coefficients: Vec<u8> = (0..digits.len())
+                        .map(|k| binomial_mod10(phases - 1 + k, k))
+                        .collect();
+                    digits = (0..count)
+                        .map(|i| {
+                            let sum: usize = digits[i..]
+                                .iter()
+                                .zip(&coefficients)
+                                .map(|(&d, &c)| d as usize * c as usize)
+                                .sum();
+                            (sum % 10) as u8
+                        })
+                        .collect();
+                }
+            }
+            Strategy::Auto => unreachable!(),
+        }
+        digits.truncate(count);
+        Ok(digits)
+    }
 }
 
-fn phase(input: &[u8], output: &mut [u8]) {
+/// Computes a phase of the digits starting at `offset` in the whole signal.
+///
+/// The pattern of output i is zero before i, so the digits before the offset are not needed.
+fn phase(input: &[u8], offset: usize, output: &mut [u8]) {
     assert_eq!(input.len(), output.len());
+    let mut prefix_sums = vec![0i64; input.len() + 1];
+    for (i, &d) in input.iter().enumerate() {
+        prefix_sums[i + 1] = prefix_sums[i] + d as i64;
+    }
+    let end = offset + input.len();
+    // sum of the digits at the absolute positions in start..end
+    let block = |start: usize, stop: usize| {
+        let (start, stop) = (start.min(end), stop.min(end));
+        prefix_sums[stop - offset] - prefix_sums[start - offset]
+    };
+
     for (i, out) in output.iter_mut().enumerate() {
-        let x: i64 = pattern_ones(i + 1)
-            .take_while(|&pos| pos < input.len() + 1)
-            .map(|pos| input[pos - 1] as i64)
-            .sum();
-        let y: i64 = pattern_minus_ones(i + 1)
-            .take_while(|&pos| pos < input.len() + 1)
-            .map(|pos| input[pos - 1] as i64)
-            .sum();
-        *out = ((x - y) % 10).abs() as u8;
+        let n = offset + i + 1;
+        let mut sum = 0;
+        // the pattern of n is n zeros, n ones, n zeros, n minus ones, ... shifted left by one
+        let mut start = n - 1;
+        while start < end {
+            sum += block(start, start + n);
+            sum -= block(start + 2 * n, start + 3 * n);
+            start += 4 * n;
+        }
+        *out = (sum % 10).unsigned_abs() as u8;
     }
 }
 
-fn part2(seq: &[u8]) -> i64 {
-    let mut seq = seq.repeat(10_000);
-    let offset: usize = to_number(seq[0..7].iter()) as usize;
-    let mut seq = seq.split_off(offset);
-
-    // The pattern at x is the following when x > len / 2
-    //
-    // [0 .. x x + 1 .. len]
-    // [0 .. 0 1     .. 1  ]
-    //
-    // That's why the calcuation is so simple, since the offset is big!
-    let mut buf = vec![0; seq.len()];
-    for _ in 0..100 {
-        let mut sum = 0;
-        for i in (0..seq.len()).rev() {
-            sum = (sum + seq[i]) % 10;
-            buf[i] = sum;
+/// C(n, k) mod p for a prime p by Lucas' theorem.
+fn binomial_mod_prime(mut n: usize, mut k: usize, p: usize) -> usize {
+    let mut result = 1;
+    while k > 0 {
+        let (ni, ki) = (n % p, k % p);
+        if ki > ni {
+            return 0;
+        }
+        // the digits are small, so the binomial is computed directly
+        let mut c = 1;
+        for j in 0..ki {
+            c = c * (ni - j) / (j + 1);
         }
-        std::mem::swap(&mut seq, &mut buf);
+        result = result * c % p;
+        n /= p;
+        k /= p;
     }
+    result
+}
 
-    to_number(seq[0..8].iter())
+/// C(n, k) mod 10 from the residues modulo 2 and 5 by the Chinese remainder theorem.
+fn binomial_mod10(n: usize, k: usize) -> u8 {
+    let (a, b) = (binomial_mod_prime(n, k, 2), binomial_mod_prime(n, k, 5));
+    ((5 * a + 6 * b) % 10) as u8
 }
 
-fn to_number<'a>(digits: impl Iterator<Item = &'a u8> + DoubleEndedIterator) -> i64 {
+fn part2(signal: &[u8]) -> Result<i64> {
+    let offset = to_number(signal.get(0..7).ok_or("signal too short")?.iter()) as usize;
+    let digits = Fft::new(signal.to_vec(), 10_000).run(100, offset, 8, Strategy::Auto)?;
+    Ok(to_number(digits.iter()))
+}
+
+fn to_number<'a>(digits: impl DoubleEndedIterator<Item = &'a u8>) -> i64 {
     digits
         .rev()
         .enumerate()
@@ -84,22 +225,64 @@ mod tests {
     fn test_phase() {
         let mut out = vec![0; 8];
 
-        phase(&vec![1, 2, 3, 4, 5, 6, 7, 8], &mut out);
+        phase(&[1, 2, 3, 4, 5, 6, 7, 8], 0, &mut out);
         assert_eq!(out, vec![4, 8, 2, 2, 6, 1, 5, 8]);
 
-        phase(&out.clone(), &mut out);
+        phase(&out.clone(), 0, &mut out);
         assert_eq!(out, vec![3, 4, 0, 4, 0, 4, 3, 8]);
+
+        let mut out = vec![0; 5];
+        phase(&[4, 5, 6, 7, 8], 3, &mut out);
+        assert_eq!(out, vec![2, 6, 1, 5, 8]);
+
+        let fft = Fft::new(parse("80871224585914546619083218645595"), 1);
+        let digits = fft.run(100, 0, 8, Strategy::Auto).unwrap();
+        assert_eq!(to_number(digits.iter()), 24176176);
     }
 
     #[test]
     fn test_part2() {
         let res = part2(&parse("03036732577212944063491565474664"));
-        assert_eq!(res, 84462026);
+        assert_eq!(res.unwrap(), 84462026);
 
         let res = part2(&parse("02935109699940807407585447034323"));
-        assert_eq!(res, 78725270);
+        assert_eq!(res.unwrap(), 78725270);
 
         let res = part2(&parse("03081770884921959731165446850517"));
-        assert_eq!(res, 53553731);
+        assert_eq!(res.unwrap(), 53553731);
+    }
+
+    #[test]
+    fn test_strategies() {
+        assert_eq!(binomial_mod10(10, 3), 0);
+        assert_eq!(binomial_mod10(7, 3), 5);
+        assert_eq!(binomial_mod10(12, 4), 5);
+
+        let fft = Fft::new(parse("5938271604"), 7);
+        let len = fft.len();
+        for &phases in &[0, 1, 3, 17] {
+            for offset in (len / 2..len - 3).step_by(5) {
+                let count = len - offset;
+                let expected = fft
+                    .run(phases, offset, count, Strategy::PrefixSums)
+                    .unwrap();
+                let suffix = fft
+                    .run(phases, offset, count, Strategy::SuffixSums)
+                    .unwrap();
+                let binomial = fft.run(phases, offset, count, Strategy::Binomial).unwrap();
+                assert_eq!(suffix, expected);
+                assert_eq!(binomial, expected);
+            }
+            // the window from an offset matches the same digits of a full run
+            let full = fft.run(phases, 0, len, Strategy::Auto).unwrap();
+            let window = fft.run(phases, 13, 10, Strategy::Auto).unwrap();
+            assert_eq!(window, &full[13..23]);
+        }
+
+        assert_eq!(fft.choose(100, 0, 8), Strategy::PrefixSums);
+        assert_eq!(fft.choose(100, 60, 8), Strategy::Binomial);
+        assert_eq!(fft.choose(2, 60, 8), Strategy::SuffixSums);
+        assert!(fft.run(1, 10, 8, Strategy::SuffixSums).is_err());
+        assert!(fft.run(1, 68, 8, Strategy::Auto).is_err());
     }
 }
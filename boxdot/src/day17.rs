@@ -1,32 +1,33 @@
+pub mod compress;
+pub mod scaffold;
+
 use crate::day09::{execute, parse, Memory};
+use crate::tui::Canvas;
+use compress::{Compressor, Encoding};
 use itertools::iproduct;
 use std::ops::Index;
 
 type Error = Box<dyn std::error::Error>;
 type Result<T> = std::result::Result<T, Error>;
-type Coord = (isize, isize);
+pub type Coord = (isize, isize);
 
 pub fn solve(input: &str) -> Result<(isize, i64)> {
     let mem = parse(input);
     let field = run(mem.clone())?;
+    println!("{}", field.canvas().render(&|&tile: &u8| tile as char));
     let part1 = alignment(&field);
 
-    let ops = find_sequence(&field)?;
-    println!("{}", ops.join(","));
-
-    // manually partitioned the graph
-    let part2 = run_robot(
-        mem,
-        "A,B,A,C,A,B,C,C,A,B",
-        "R,8,L,10,R,8",
-        "R,12,R,8,L,8,L,12",
-        "L,12,L,10,L,8",
-    )?;
+    let encoding = Compressor::default()
+        .encode(&field)?
+        .ok_or("scaffold cannot be encoded")?;
+    println!("{}", encoding);
+    let part2 = run_robot(mem, &encoding)?;
 
     Ok((part1, part2))
 }
 
-struct Field {
+/// Camera image of the scaffold.
+pub struct Field {
     data: Vec<u8>,
     width: usize,
     height: usize,
@@ -41,22 +42,69 @@ impl Index<Coord> for Field {
 }
 
 impl Field {
-    fn is_scaffold(&self, (x, y): Coord) -> bool {
+    /// Parses a camera image made of `.`, `#`, the robot `^v<>` on the scaffold or a tumbling
+    /// robot `X`.
+    pub fn parse(image: &str) -> Result<Self> {
+        let lines: Vec<&str> = image
+            .trim_end_matches('\n')
+            .lines()
+            .take_while(|l| !l.is_empty())
+            .collect();
+        let width = lines.first().map_or(0, |l| l.len());
+        let mut data = Vec::with_capacity(width * lines.len());
+        for (y, line) in lines.iter().enumerate() {
+            if line.len() != width {
+                return Err(
+                    format!("line {} has width {} instead of {}", y, line.len(), width).into(),
+                );
+            }
+            if let Some(c) = line.chars().find(|c| !".#^v<>X".contains(*c)) {
+                return Err(format!("invalid character {:?} in line {}", c, y).into());
+            }
+            data.extend(line.bytes());
+        }
+        Ok(Self {
+            data,
+            width,
+            height: lines.len(),
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_scaffold(&self, (x, y): Coord) -> bool {
         if 0 <= x && x < self.width as isize && 0 <= y && y < self.height as isize {
-            self[(x, y)] == b'#'
+            matches!(self[(x, y)], b'#' | b'^' | b'v' | b'<' | b'>')
         } else {
             false
         }
     }
 
+    /// Camera image with the intersections marked as `O`.
+    pub fn canvas(&self) -> Canvas<u8> {
+        let mut canvas: Canvas<u8> = self
+            .iter()
+            .map(|((x, y), tile)| ((x as i64, y as i64), tile))
+            .collect();
+        for (x, y) in self.intersections() {
+            canvas.set((x as i64, y as i64), b'O');
+        }
+        canvas
+    }
+
     fn iter(&self) -> impl Iterator<Item = (Coord, u8)> + '_ {
         iproduct!(0..self.width as isize, 0..self.height as isize).map(move |pos| (pos, self[pos]))
     }
 }
 
 fn run(mut mem: Memory) -> Result<Field> {
-    let mut field = Vec::new();
-    let mut width = None;
+    let mut image = String::new();
 
     let mut ip = Some(0);
     while let Some(next_ip) = ip {
@@ -64,109 +112,31 @@ fn run(mut mem: Memory) -> Result<Field> {
             &mut mem,
             next_ip,
             || 0,
-            |value| {
-                let c = value as u8;
-                print!("{}", c as char);
-                if c != b'\n' {
-                    field.push(c);
-                } else if width.is_none() {
-                    width = Some(field.len());
-                }
-            },
+            |value| image.push(value as u8 as char),
         )?;
     }
 
-    let width = width.unwrap_or_else(|| field.len());
-    let height = field.len() / width;
-    Ok(Field {
-        data: field,
-        width,
-        height,
-    })
+    Field::parse(&image)
 }
 
 fn alignment(field: &Field) -> isize {
-    field
-        .iter()
-        .filter_map(|((x, y), _)| {
-            let is_intersection = [(x, y), (x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
-                .iter()
-                .all(|&pos| field.is_scaffold(pos));
-            if is_intersection {
-                Some(x * y)
-            } else {
-                None
-            }
-        })
-        .sum()
+    field.intersections().iter().map(|(x, y)| x * y).sum()
 }
 
-fn find_sequence(field: &Field) -> Result<Vec<String>> {
-    let mut ops = Vec::new();
-
-    let (mut robot_pos, mut robot_state) = field
-        .iter()
-        .find(|(_pos, value)| match value {
-            b'^' | b'v' | b'<' | b'>' => true,
-            _ => false,
-        })
-        .ok_or_else(|| "no robot found")?;
-
-    loop {
-        // move
-        let mut pos = robot_pos;
-        while let Some(coord) = walk(field, pos, robot_state) {
-            pos = coord;
-        }
-        let dist = (robot_pos.0 - pos.0).abs() + (robot_pos.1 - pos.1).abs();
-        if dist != 0 {
-            ops.push(dist.to_string());
-            robot_pos = pos;
-        }
-
-        // turn
-        let (x, y) = robot_pos;
-        let (op, new_state) = match robot_state {
-            b'^' if field.is_scaffold((x + 1, y)) => ("R", b'>'),
-            b'^' if field.is_scaffold((x - 1, y)) => ("L", b'<'),
-
-            b'v' if field.is_scaffold((x + 1, y)) => ("L", b'>'),
-            b'v' if field.is_scaffold((x - 1, y)) => ("R", b'<'),
-
-            b'<' if field.is_scaffold((x, y - 1)) => ("R", b'^'),
-            b'<' if field.is_scaffold((x, y + 1)) => ("L", b'v'),
-
-            b'>' if field.is_scaffold((x, y - 1)) => ("L", b'^'),
-            b'>' if field.is_scaffold((x, y + 1)) => ("R", b'v'),
-
-            _ => break,
-        };
-        ops.push(op.to_string());
-        robot_state = new_state;
-    }
-
-    Ok(ops)
+fn turn_right((dx, dy): Coord) -> Coord {
+    (-dy, dx)
 }
 
-fn walk(field: &Field, (x, y): Coord, state: u8) -> Option<Coord> {
-    let coord = match state {
-        b'^' => (x, y - 1),
-        b'v' => (x, y + 1),
-        b'<' => (x - 1, y),
-        b'>' => (x + 1, y),
-        _ => unreachable!(),
-    };
-    if field.is_scaffold(coord) {
-        Some(coord)
-    } else {
-        None
-    }
+fn turn_left((dx, dy): Coord) -> Coord {
+    (dy, -dx)
 }
 
-fn run_robot(mut mem: Memory, main: &str, a: &str, b: &str, c: &str) -> Result<i64> {
+fn run_robot(mut mem: Memory, encoding: &Encoding) -> Result<i64> {
     mem.write(0, 2);
 
-    let input = format!("{}\n{}\n{}\n{}\nn\n", main, a, b, c).into_bytes();
+    let mut input = encoding.lines(3)?.join("\n");
+    input.push_str("\nn\n");
+    let input = input.into_bytes();
     let mut input_pos = 0;
 
     let mut out = 0;
@@ -189,3 +159,32 @@ fn run_robot(mut mem: Memory, main: &str, a: &str, b: &str, c: &str) -> Result<i
 
     Ok(out)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+..#..........
+..#..........
+#######...###
+#.#...#...#.#
+#############
+..#...#...#..
+..#####...^..
+";
+
+    #[test]
+    fn test_alignment() {
+        let field = Field::parse(EXAMPLE).unwrap();
+        assert_eq!(alignment(&field), 76);
+    }
+
+    #[test]
+    fn test_canvas() {
+        let canvas = Field::parse(EXAMPLE).unwrap().canvas();
+        assert_eq!(canvas.get((2, 2)), Some(&b'O'));
+        assert_eq!(canvas.get((10, 6)), Some(&b'^'));
+        assert_eq!(canvas.render(&|&tile: &u8| tile as char).lines().count(), 7);
+    }
+}
@@ -0,0 +1,486 @@
+//! Compression of scaffold walks into a main routine and movement functions.
+//!
+//! A walk is a list of turns and forward moves. It is encoded as a main routine calling up to
+//! `max_routines` functions named `A`, `B`, ..., where every line fits into `max_len` characters
+//! when written comma-separated. Since the robot may go straight or turn at an intersection, a
+//! scaffold usually has several walks, and only some of them are compressible.
+
+use super::{turn_left, turn_right, Coord, Field};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Token {
+    Left,
+    Right,
+    Forward(usize),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Left => write!(f, "L"),
+            Token::Right => write!(f, "R"),
+            Token::Forward(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl FromStr for Token {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "L" => Ok(Token::Left),
+            "R" => Ok(Token::Right),
+            _ => s
+                .parse()
+                .map(Token::Forward)
+                .map_err(|_| format!("invalid movement: {:?}", s).into()),
+        }
+    }
+}
+
+/// Parses a comma-separated movement function.
+pub fn parse(line: &str) -> Result<Vec<Token>> {
+    line.split(',').map(|t| t.trim().parse()).collect()
+}
+
+/// Comma-separated form of the tokens.
+pub fn join(tokens: &[Token]) -> String {
+    let tokens: Vec<_> = tokens.iter().map(Token::to_string).collect();
+    tokens.join(",")
+}
+
+fn encoded_len(tokens: &[Token]) -> usize {
+    tokens
+        .iter()
+        .map(|t| t.to_string().len() + 1)
+        .sum::<usize>()
+        - 1
+}
+
+/// Main routine as indices into the movement functions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Encoding {
+    pub main: Vec<usize>,
+    pub routines: Vec<Vec<Token>>,
+}
+
+impl Encoding {
+    pub fn main_line(&self) -> String {
+        let calls: Vec<_> = self
+            .main
+            .iter()
+            .map(|&i| ((b'A' + i as u8) as char).to_string())
+            .collect();
+        calls.join(",")
+    }
+
+    /// The walk which the encoding describes.
+    pub fn expand(&self) -> Vec<Token> {
+        self.main
+            .iter()
+            .flat_map(|&i| self.routines[i].iter().copied())
+            .collect()
+    }
+
+    /// Main routine followed by exactly `num_routines` function lines.
+    ///
+    /// Missing functions repeat the first one, or are empty without any function; they are never
+    /// called.
+    pub fn lines(&self, num_routines: usize) -> Result<Vec<String>> {
+        if self.routines.len() > num_routines {
+            return Err(format!(
+                "encoding needs {} functions, but only {} are available",
+                self.routines.len(),
+                num_routines
+            )
+            .into());
+        }
+        let mut lines = vec![self.main_line()];
+        lines.extend((0..num_routines).map(|i| {
+            join(
+                self.routines
+                    .get(i)
+                    .or(self.routines.first())
+                    .map_or(&[][..], Vec::as_slice),
+            )
+        }));
+        Ok(lines)
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.main_line())?;
+        for routine in &self.routines {
+            write!(f, "\n{}", join(routine))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compressor {
+    max_routines: usize,
+    max_len: usize,
+}
+
+impl Default for Compressor {
+    /// Three functions and 20 characters per line as accepted by the vacuum robot.
+    fn default() -> Self {
+        Self {
+            max_routines: 3,
+            max_len: 20,
+        }
+    }
+}
+
+impl Compressor {
+    pub fn new(max_routines: usize, max_len: usize) -> Result<Self> {
+        if max_routines == 0 || max_routines > 26 {
+            return Err(format!("cannot name {} functions with letters", max_routines).into());
+        }
+        if max_len == 0 {
+            return Err("lines must hold at least one character".into());
+        }
+        Ok(Self {
+            max_routines,
+            max_len,
+        })
+    }
+
+    /// First encoding of the tokens, trying longer functions last.
+    pub fn compress(&self, tokens: &[Token]) -> Option<Encoding> {
+        let mut found = None;
+        self.search(tokens, &mut |encoding| {
+            found = Some(encoding.clone());
+            true
+        });
+        found
+    }
+
+    /// All encodings of the tokens; functions are numbered in the order of their first call.
+    pub fn compress_all(&self, tokens: &[Token]) -> Vec<Encoding> {
+        let mut found = Vec::new();
+        self.search(tokens, &mut |encoding| {
+            found.push(encoding.clone());
+            false
+        });
+        found
+    }
+
+    /// First encoding of any walk covering the scaffold.
+    pub fn encode(&self, field: &Field) -> Result<Option<Encoding>> {
+        Ok(walks(field)?.find_map(|walk| self.compress(&walk)))
+    }
+
+    /// Encodings of all walks covering the scaffold, without duplicates.
+    pub fn encode_all(&self, field: &Field) -> Result<Vec<Encoding>> {
+        let mut found = Vec::new();
+        for walk in walks(field)? {
+            for encoding in self.compress_all(&walk) {
+                if !found.contains(&encoding) {
+                    found.push(encoding);
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    /// Calls `visit` for each encoding until it returns true.
+    fn search(&self, tokens: &[Token], visit: &mut dyn FnMut(&Encoding) -> bool) -> bool {
+        let mut encoding = Encoding {
+            main: Vec::new(),
+            routines: Vec::new(),
+        };
+        !tokens.is_empty() && self.search_from(tokens, 0, &mut encoding, visit)
+    }
+
+    fn search_from(
+        &self,
+        tokens: &[Token],
+        pos: usize,
+        encoding: &mut Encoding,
+        visit: &mut dyn FnMut(&Encoding) -> bool,
+    ) -> bool {
+        if pos == tokens.len() {
+            return visit(encoding);
+        }
+        // every call takes a letter and a comma
+        if 2 * encoding.main.len() + 1 > self.max_len {
+            return false;
+        }
+        let rest = &tokens[pos..];
+
+        for i in 0..encoding.routines.len() {
+            if rest.starts_with(&encoding.routines[i]) {
+                let len = encoding.routines[i].len();
+                encoding.main.push(i);
+                if self.search_from(tokens, pos + len, encoding, visit) {
+                    return true;
+                }
+                encoding.main.pop();
+            }
+        }
+
+        if encoding.routines.len() < self.max_routines {
+            for end in 1..=rest.len() {
+                let routine = &rest[..end];
+                if encoded_len(routine) > self.max_len {
+                    break;
+                }
+                if encoding.routines.iter().any(|r| r == routine) {
+                    continue;
+                }
+                encoding.main.push(encoding.routines.len());
+                encoding.routines.push(routine.to_vec());
+                if self.search_from(tokens, pos + end, encoding, visit) {
+                    return true;
+                }
+                encoding.routines.pop();
+                encoding.main.pop();
+            }
+        }
+        false
+    }
+}
+
+/// Walks from the robot which pass every scaffold edge exactly once.
+///
+/// At each cell the robot goes straight if possible, otherwise right and then left, so the first
+/// walk is the greedy one. Only at the start, the robot may turn around.
+pub fn walks(field: &Field) -> Result<Walks> {
+    let pose = field.robot()?;
+    let (start, dir) = (pose.pos, pose.dir.ok_or("robot is tumbling through space")?);
+    let mut edges = HashMap::new();
+    for (pos, _) in field.iter().filter(|&(pos, _)| field.is_scaffold(pos)) {
+        for &next in &[(pos.0 + 1, pos.1), (pos.0, pos.1 + 1)] {
+            if field.is_scaffold(next) {
+                let id = edges.len();
+                edges.insert((pos, next), id);
+            }
+        }
+    }
+    let mut walks = Walks {
+        used: vec![false; edges.len()],
+        edges,
+        num_used: 0,
+        steps: Vec::new(),
+        stack: Vec::new(),
+    };
+    let choices = walks.choices(start, dir, true);
+    walks.stack.push(Frame {
+        pos: start,
+        dir,
+        choices,
+        next: 0,
+        edge: None,
+        num_steps: 0,
+    });
+    Ok(walks)
+}
+
+pub struct Walks {
+    edges: HashMap<(Coord, Coord), usize>,
+    used: Vec<bool>,
+    num_used: usize,
+    /// Single turns and unit moves of the current walk.
+    steps: Vec<Token>,
+    stack: Vec<Frame>,
+}
+
+struct Frame {
+    pos: Coord,
+    dir: Coord,
+    choices: Vec<Coord>,
+    next: usize,
+    /// Edge by which the cell was entered.
+    edge: Option<usize>,
+    /// Length of the walk before entering the cell.
+    num_steps: usize,
+}
+
+impl Walks {
+    fn edge(&self, a: Coord, b: Coord) -> Option<usize> {
+        let key = if a < b { (a, b) } else { (b, a) };
+        self.edges.get(&key).copied()
+    }
+
+    fn choices(&self, pos: Coord, dir: Coord, may_reverse: bool) -> Vec<Coord> {
+        let mut dirs = vec![dir, turn_right(dir), turn_left(dir)];
+        if may_reverse {
+            dirs.push((-dir.0, -dir.1));
+        }
+        dirs.into_iter()
+            .filter(|&(dx, dy)| {
+                self.edge(pos, (pos.0 + dx, pos.1 + dy))
+                    .is_some_and(|edge| !self.used[edge])
+            })
+            .collect()
+    }
+
+    fn tokens(&self) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        for &step in &self.steps {
+            match (tokens.last_mut(), step) {
+                (Some(Token::Forward(n)), Token::Forward(m)) => *n += m,
+                _ => tokens.push(step),
+            }
+        }
+        tokens
+    }
+}
+
+impl Iterator for Walks {
+    type Item = Vec<Token>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let frame = self.stack.last_mut()?;
+            if frame.next < frame.choices.len() {
+                let dir = frame.choices[frame.next];
+                frame.next += 1;
+                let (pos, prev_dir) = (frame.pos, frame.dir);
+                let next = (pos.0 + dir.0, pos.1 + dir.1);
+                let edge = self.edge(pos, next).unwrap();
+
+                let num_steps = self.steps.len();
+                if dir == turn_right(prev_dir) {
+                    self.steps.push(Token::Right);
+                } else if dir == turn_left(prev_dir) {
+                    self.steps.push(Token::Left);
+                } else if dir != prev_dir {
+                    self.steps.extend_from_slice(&[Token::Right, Token::Right]);
+                }
+                self.steps.push(Token::Forward(1));
+                self.used[edge] = true;
+                self.num_used += 1;
+
+                let choices = self.choices(next, dir, false);
+                let is_complete = choices.is_empty() && self.num_used == self.used.len();
+                self.stack.push(Frame {
+                    pos: next,
+                    dir,
+                    choices,
+                    next: 0,
+                    edge: Some(edge),
+                    num_steps,
+                });
+                if is_complete {
+                    return Some(self.tokens());
+                }
+            } else {
+                let frame = self.stack.pop().unwrap();
+                if let Some(edge) = frame.edge {
+                    self.used[edge] = false;
+                    self.num_used -= 1;
+                }
+                self.steps.truncate(frame.num_steps);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+#######...#####
+#.....#...#...#
+#.....#...#...#
+......#...#...#
+......#...###.#
+......#.....#.#
+^########...#.#
+......#.#...#.#
+......#########
+........#...#..
+....#########..
+....#...#......
+....#...#......
+....#...#......
+....#####......";
+
+    #[test]
+    fn test_walks() {
+        let field = Field::parse(EXAMPLE).unwrap();
+        let first = walks(&field).unwrap().next().unwrap();
+        assert_eq!(
+            join(&first),
+            "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2"
+        );
+        let all: Vec<_> = walks(&field).unwrap().collect();
+        assert!(all.len() > 1);
+        assert!(all.iter().all(|w| w
+            .iter()
+            .filter_map(|t| match t {
+                Token::Forward(n) => Some(n),
+                _ => None,
+            })
+            .sum::<usize>()
+            == 80));
+    }
+
+    #[test]
+    fn test_compress() {
+        let field = Field::parse(EXAMPLE).unwrap();
+        let walk = walks(&field).unwrap().next().unwrap();
+        let compressor = Compressor::default();
+        let encoding = compressor.compress(&walk).unwrap();
+        assert_eq!(encoding.expand(), walk);
+        assert!(encoding.lines(3).unwrap().iter().all(|l| l.len() <= 20));
+
+        let all = compressor.compress_all(&walk);
+        assert!(all.contains(&Encoding {
+            main: vec![0, 1, 2, 1, 0, 2],
+            routines: vec![
+                walk[0..4].to_vec(),
+                walk[4..10].to_vec(),
+                walk[10..14].to_vec(),
+            ],
+        }));
+        assert!(all.iter().all(|e| e.expand() == walk));
+
+        // a single short function cannot cover the walk
+        let compressor = Compressor::new(1, 20).unwrap();
+        assert_eq!(compressor.compress(&walk), None);
+        assert!(Compressor::new(27, 20).is_err());
+
+        let encodings = Compressor::default().encode_all(&field).unwrap();
+        assert!(encodings.len() >= all.len());
+        assert_eq!(
+            Compressor::default().encode(&field).unwrap().as_ref(),
+            Some(&encoding)
+        );
+    }
+
+    #[test]
+    fn test_lines() {
+        let encoding = Encoding {
+            main: vec![0, 1, 0],
+            routines: vec![
+                vec![Token::Right, Token::Forward(12)],
+                vec![Token::Left, Token::Forward(4)],
+            ],
+        };
+        assert_eq!(
+            encoding.lines(3).unwrap(),
+            vec!["A,B,A", "R,12", "L,4", "R,12"]
+        );
+        assert_eq!(encoding.to_string(), "A,B,A\nR,12\nL,4");
+        assert!(encoding.lines(1).is_err());
+
+        let empty = Encoding {
+            main: Vec::new(),
+            routines: Vec::new(),
+        };
+        assert_eq!(empty.lines(2).unwrap(), vec!["", "", ""]);
+    }
+}
@@ -0,0 +1,350 @@
+//! Analysis of the scaffold in a camera image, independent of the Intcode program.
+
+use super::compress::{self, Token};
+use super::{turn_left, turn_right, Coord, Field};
+use std::collections::HashSet;
+
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
+
+const DIRECTIONS: [Coord; 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+/// Position of the robot and where it faces; a tumbling robot faces nowhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pose {
+    pub pos: Coord,
+    pub dir: Option<Coord>,
+}
+
+/// Maximal horizontal or vertical run of scaffold cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Straight {
+    pub start: Coord,
+    /// Either `(1, 0)` or `(0, 1)`.
+    pub dir: Coord,
+    /// Number of moves from the first to the last cell.
+    pub len: usize,
+}
+
+/// Scaffold between two nodes, which are cells with other than two neighbors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub from: usize,
+    pub to: usize,
+    /// All cells from the node `from` to the node `to`.
+    pub cells: Vec<Coord>,
+}
+
+impl Segment {
+    /// Number of moves along the segment.
+    pub fn len(&self) -> usize {
+        self.cells.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Scaffold as a graph of junctions and dead ends connected by segments.
+///
+/// A closed loop without any junction is represented by its smallest cell as a node.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Graph {
+    pub nodes: Vec<Coord>,
+    pub segments: Vec<Segment>,
+}
+
+impl Graph {
+    pub fn node(&self, pos: Coord) -> Option<usize> {
+        self.nodes.iter().position(|&node| node == pos)
+    }
+
+    /// Segments starting or ending at the node; loops are returned once.
+    pub fn segments_at(&self, node: usize) -> impl Iterator<Item = &Segment> {
+        self.segments
+            .iter()
+            .filter(move |s| s.from == node || s.to == node)
+    }
+}
+
+fn step((x, y): Coord, (dx, dy): Coord) -> Coord {
+    (x + dx, y + dy)
+}
+
+fn edge(a: Coord, b: Coord) -> (Coord, Coord) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+impl Field {
+    pub fn robot(&self) -> Result<Pose> {
+        self.iter()
+            .find_map(|(pos, value)| {
+                let dir = match value {
+                    b'^' => Some((0, -1)),
+                    b'v' => Some((0, 1)),
+                    b'<' => Some((-1, 0)),
+                    b'>' => Some((1, 0)),
+                    b'X' => None,
+                    _ => return None,
+                };
+                Some(Pose { pos, dir })
+            })
+            .ok_or_else(|| "no robot found".into())
+    }
+
+    pub fn scaffold(&self) -> impl Iterator<Item = Coord> + '_ {
+        self.iter()
+            .map(|(pos, _)| pos)
+            .filter(move |&pos| self.is_scaffold(pos))
+    }
+
+    pub fn neighbors(&self, pos: Coord) -> impl Iterator<Item = Coord> + '_ {
+        DIRECTIONS
+            .iter()
+            .map(move |&dir| step(pos, dir))
+            .filter(move |&next| self.is_scaffold(next))
+    }
+
+    pub fn degree(&self, pos: Coord) -> usize {
+        self.neighbors(pos).count()
+    }
+
+    /// Scaffold cells with scaffold on all four sides.
+    pub fn intersections(&self) -> Vec<Coord> {
+        self.scaffold()
+            .filter(|&pos| self.degree(pos) == 4)
+            .collect()
+    }
+
+    /// Scaffold cells with at most one neighbor.
+    pub fn dead_ends(&self) -> Vec<Coord> {
+        self.scaffold()
+            .filter(|&pos| self.degree(pos) <= 1)
+            .collect()
+    }
+
+    /// Horizontal runs followed by vertical runs, each of at least two cells.
+    pub fn straights(&self) -> Vec<Straight> {
+        let mut straights = Vec::new();
+        for &dir in &[(1, 0), (0, 1)] {
+            let mut starts: Vec<Coord> = self
+                .scaffold()
+                .filter(|&pos| !self.is_scaffold((pos.0 - dir.0, pos.1 - dir.1)))
+                .filter(|&pos| self.is_scaffold(step(pos, dir)))
+                .collect();
+            starts.sort_by_key(|&(x, y)| (y, x));
+            for start in starts {
+                let mut len = 0;
+                let mut pos = start;
+                while self.is_scaffold(step(pos, dir)) {
+                    pos = step(pos, dir);
+                    len += 1;
+                }
+                straights.push(Straight { start, dir, len });
+            }
+        }
+        straights
+    }
+
+    pub fn graph(&self) -> Graph {
+        let mut graph = Graph::default();
+        let mut cells: Vec<Coord> = self.scaffold().collect();
+        cells.sort_by_key(|&(x, y)| (y, x));
+        graph.nodes = cells
+            .iter()
+            .copied()
+            .filter(|&pos| self.degree(pos) != 2)
+            .collect();
+
+        let mut visited = HashSet::new();
+        let mut node = 0;
+        loop {
+            while node < graph.nodes.len() {
+                let from = graph.nodes[node];
+                for first in self.neighbors(from).collect::<Vec<_>>() {
+                    if !visited.insert(edge(from, first)) {
+                        continue;
+                    }
+                    let mut segment = vec![from, first];
+                    let mut pos = first;
+                    while graph.node(pos).is_none() {
+                        let prev = segment[segment.len() - 2];
+                        let next = self.neighbors(pos).find(|&n| n != prev).unwrap();
+                        visited.insert(edge(pos, next));
+                        segment.push(next);
+                        pos = next;
+                    }
+                    graph.segments.push(Segment {
+                        from: node,
+                        to: graph.node(pos).unwrap(),
+                        cells: segment,
+                    });
+                }
+                node += 1;
+            }
+            // the remaining cells form loops without junctions
+            let unvisited = cells.iter().find(|&&pos| {
+                self.neighbors(pos)
+                    .any(|next| !visited.contains(&edge(pos, next)))
+            });
+            match unvisited {
+                Some(&pos) => graph.nodes.push(pos),
+                None => break,
+            }
+        }
+        graph
+    }
+
+    /// Cells passed by the robot when following the movements from the pose, including the start.
+    pub fn trace(&self, pose: Pose, tokens: &[Token]) -> Result<(Vec<Coord>, Pose)> {
+        let mut dir = pose.dir.ok_or("robot is tumbling through space")?;
+        let mut pos = pose.pos;
+        let mut path = vec![pos];
+        for &token in tokens {
+            match token {
+                Token::Left => dir = turn_left(dir),
+                Token::Right => dir = turn_right(dir),
+                Token::Forward(n) => {
+                    for _ in 0..n {
+                        pos = step(pos, dir);
+                        if !self.is_scaffold(pos) {
+                            return Err(format!("robot falls off the scaffold at {:?}", pos).into());
+                        }
+                        path.push(pos);
+                    }
+                }
+            }
+        }
+        Ok((
+            path,
+            Pose {
+                pos,
+                dir: Some(dir),
+            },
+        ))
+    }
+
+    /// Checks that the movement program makes the robot visit every scaffold cell.
+    pub fn validate(&self, main: &str, routines: &[&str]) -> Result<()> {
+        let routines = routines
+            .iter()
+            .map(|r| compress::parse(r))
+            .collect::<Result<Vec<_>>>()?;
+        let mut tokens = Vec::new();
+        for call in main.split(',').map(str::trim) {
+            let routine = match call.as_bytes() {
+                [c @ b'A'..=b'Z'] => routines.get((c - b'A') as usize),
+                _ => None,
+            };
+            tokens.extend(routine.ok_or_else(|| format!("unknown function {:?}", call))?);
+        }
+
+        let (path, _) = self.trace(self.robot()?, &tokens)?;
+        let visited: HashSet<Coord> = path.into_iter().collect();
+        let missed: Vec<Coord> = self
+            .scaffold()
+            .filter(|pos| !visited.contains(pos))
+            .collect();
+        if let Some(&first) = missed.iter().min_by_key(|&&(x, y)| (y, x)) {
+            return Err(format!(
+                "{} scaffold cells are not visited, first at {:?}",
+                missed.len(),
+                first
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+..#..........
+..#..........
+#######...###
+#.#...#...#.#
+#############
+..#...#...#..
+..#####...^..
+";
+
+    #[test]
+    fn test_analysis() {
+        let field = Field::parse(EXAMPLE).unwrap();
+        assert_eq!((field.width(), field.height()), (13, 7));
+        assert_eq!(
+            field.robot().unwrap(),
+            Pose {
+                pos: (10, 6),
+                dir: Some((0, -1))
+            }
+        );
+        let mut intersections = field.intersections();
+        intersections.sort();
+        assert_eq!(intersections, vec![(2, 2), (2, 4), (6, 4), (10, 4)]);
+
+        let mut dead_ends = field.dead_ends();
+        dead_ends.sort();
+        assert_eq!(dead_ends, vec![(2, 0), (10, 6)]);
+
+        let straights = field.straights();
+        assert_eq!(
+            straights[0],
+            Straight {
+                start: (0, 2),
+                dir: (1, 0),
+                len: 6
+            }
+        );
+        assert!(straights.contains(&Straight {
+            start: (2, 0),
+            dir: (0, 1),
+            len: 6
+        }));
+
+        let tumbling = Field::parse(&EXAMPLE.replace('^', "X")).unwrap();
+        assert_eq!(tumbling.robot().unwrap().dir, None);
+        assert!(!tumbling.is_scaffold((10, 6)));
+        assert!(Field::parse("#.\n#").is_err());
+        assert!(Field::parse("#?").is_err());
+    }
+
+    #[test]
+    fn test_graph() {
+        let graph = Field::parse(EXAMPLE).unwrap().graph();
+        assert_eq!(graph.nodes.len(), 6);
+        assert_eq!(graph.segments.len(), 9);
+        // every cell except the nodes lies inside exactly one segment
+        let cells: usize = graph.segments.iter().map(|s| s.cells.len() - 2).sum();
+        assert_eq!(cells + graph.nodes.len(), 39);
+        let moves: usize = graph.segments.iter().map(Segment::len).sum();
+        assert_eq!(moves, 42);
+        let junction = graph.node((6, 4)).unwrap();
+        assert_eq!(graph.segments_at(junction).count(), 4);
+
+        // a loop without junctions
+        let graph = Field::parse("###\n#.#\n###").unwrap().graph();
+        assert_eq!(graph.nodes, vec![(0, 0)]);
+        assert_eq!(graph.segments.len(), 1);
+        assert_eq!(graph.segments[0].len(), 8);
+    }
+
+    #[test]
+    fn test_validate() {
+        let field = Field::parse(EXAMPLE).unwrap();
+        let routines = ["4,R,2,R,2,R,12", "R,2,R,6,R,4,R,4,R,6"];
+        field.validate("A,B", &routines).unwrap();
+        let err = field.validate("A", &routines).unwrap_err();
+        assert!(err.to_string().contains("scaffold cells are not visited"));
+        assert!(field.validate("A,C", &routines).is_err());
+        assert!(field.validate("B", &routines).is_err());
+    }
+}
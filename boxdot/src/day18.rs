@@ -2,29 +2,145 @@ use std::cmp::Reverse;
 use std::collections::hash_map::Entry;
 use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 
-pub fn solve(input: &str) -> Option<(usize, usize)> {
-    let vault = parse(input);
-    let part1 = shortest_path_len(&vault)?;
-    let part2 = shortest_path_len4(vault)?;
-    Some((part1, part2))
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
+
+pub fn solve(input: &str) -> Result<(usize, usize)> {
+    let mut vault = Vault::parse(input)?;
+    let part1 = shortest_path(&vault).ok_or("keys cannot be collected")?;
+    vault.split_entrance()?;
+    let part2 = shortest_path(&vault).ok_or("keys cannot be collected by four robots")?;
+    Ok((part1.len, part2.len))
 }
 
-struct Vault {
+pub type Coord = (usize, usize);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vault {
     map: Vec<u8>,
     width: usize,
+    height: usize,
 }
 
-fn parse(input: &str) -> Vault {
-    let width = input.lines().next().unwrap().len();
-    let map: Vec<_> = input.lines().flat_map(|l| l.bytes()).collect();
-    Vault { map, width }
+impl Vault {
+    /// Parses a map of walls `#`, open space `.`, entrances `@`, keys `a-z` and doors `A-Z`.
+    pub fn parse(input: &str) -> Result<Self> {
+        let lines: Vec<&str> = input.lines().filter(|l| !l.is_empty()).collect();
+        let width = lines.first().map_or(0, |l| l.len());
+        let mut map = Vec::with_capacity(width * lines.len());
+        let mut keys = 0_u32;
+        for (y, line) in lines.iter().enumerate() {
+            if line.len() != width {
+                return Err(
+                    format!("line {} has width {} instead of {}", y, line.len(), width).into(),
+                );
+            }
+            for c in line.bytes() {
+                match c {
+                    b'#' | b'.' | b'@' | b'A'..=b'Z' => {}
+                    b'a'..=b'z' => {
+                        let key = 1 << (c - b'a');
+                        if keys & key != 0 {
+                            return Err(format!("duplicate key {}", c as char).into());
+                        }
+                        keys |= key;
+                    }
+                    _ => return Err(format!("invalid tile {:?} in line {}", c as char, y).into()),
+                }
+                map.push(c);
+            }
+        }
+        Ok(Self {
+            map,
+            width,
+            height: lines.len(),
+        })
+    }
+
+    fn get(&self, (x, y): Coord) -> u8 {
+        self.map[x + self.width * y]
+    }
+
+    fn set(&mut self, (x, y): Coord, tile: u8) {
+        self.map[x + self.width * y] = tile;
+    }
+
+    fn coord(&self, index: usize) -> Coord {
+        (index % self.width, index / self.width)
+    }
+
+    fn positions(&self, f: impl Fn(u8) -> bool) -> Vec<Coord> {
+        (0..self.map.len())
+            .filter(|&i| f(self.map[i]))
+            .map(|i| self.coord(i))
+            .collect()
+    }
+
+    /// Start positions of the robots in reading order.
+    pub fn entrances(&self) -> Vec<Coord> {
+        self.positions(|tile| tile == b'@')
+    }
+
+    /// Positions of all keys in reading order.
+    pub fn keys(&self) -> Vec<Coord> {
+        self.positions(|tile| tile.is_ascii_lowercase())
+    }
+
+    /// Walls off the single entrance and puts four robots diagonally next to it.
+    pub fn split_entrance(&mut self) -> Result<()> {
+        let (x, y) = match self.entrances()[..] {
+            [entrance] => entrance,
+            ref entrances => {
+                return Err(format!("expected one entrance, found {}", entrances.len()).into())
+            }
+        };
+        if x == 0 || y == 0 || x + 1 >= self.width || y + 1 >= self.height {
+            return Err("entrance at the border cannot be split".into());
+        }
+        let area: Vec<Coord> = (y - 1..=y + 1)
+            .flat_map(|y| (x - 1..=x + 1).map(move |x| (x, y)))
+            .collect();
+        if area
+            .iter()
+            .any(|&pos| !matches!(self.get(pos), b'.' | b'@'))
+        {
+            return Err("entrance is not surrounded by open space".into());
+        }
+        for &pos in &area {
+            let (dx, dy) = (pos.0 != x, pos.1 != y);
+            self.set(pos, if dx && dy { b'@' } else { b'#' });
+        }
+        Ok(())
+    }
+
+    fn neighbors(&self, (x, y): Coord) -> impl Iterator<Item = Coord> + '_ {
+        let candidates = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        IntoIterator::into_iter(candidates)
+            .filter(move |&(x, y)| x < self.width && y < self.height)
+            .filter(move |&pos| self.get(pos) != b'#')
+    }
 }
 
-type Coord = (u8, u8);
+/// Shortest way from one position to a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Path {
+    pub dest: Coord,
+    pub dist: usize,
+    pub key: u32,
+    /// Doors on the way.
+    pub doors: u32,
+    /// Keys passed on the way before the destination.
+    pub keys: u32,
+}
 
 struct PathFinder<'a> {
     vault: &'a Vault,
-    q: VecDeque<(Coord, usize, u32)>,
+    q: VecDeque<(Coord, usize, u32, u32)>,
     seen: HashSet<Coord>,
 }
 
@@ -38,42 +154,37 @@ impl<'a> PathFinder<'a> {
     }
 
     /// Finds all paths from `orig` to keys.
-    ///
-    /// Returns the list of (dest, dist, key, doors on the path).
-    fn bfs(&mut self, orig: Coord, keys: &HashMap<Coord, u32>) -> Vec<(Coord, usize, u32, u32)> {
-        let width = self.vault.width;
-        let index = |(x, y)| x as usize + width * y as usize;
-
+    fn bfs(&mut self, orig: Coord) -> Vec<Path> {
         self.q.clear();
-        self.q.push_back((orig, 0, 0));
+        self.q.push_back((orig, 0, 0, 0));
         self.seen.clear();
         self.seen.insert(orig);
 
         let mut paths = Vec::new();
-        while let Some((pos, dist, doors)) = self.q.pop_front() {
-            if let Some(&key) = keys.get(&pos) {
-                paths.push((pos, dist, key, doors));
+        while let Some((pos, dist, doors, keys)) = self.q.pop_front() {
+            let tile = self.vault.get(pos);
+            let mut keys = keys;
+            if tile.is_ascii_lowercase() && pos != orig {
+                let key = 1 << (tile - b'a');
+                paths.push(Path {
+                    dest: pos,
+                    dist,
+                    key,
+                    doors,
+                    keys,
+                });
+                keys |= key;
             }
 
-            let (x, y) = pos;
-            let neighbors = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)];
-            for &pos in neighbors.iter() {
-                if self.seen.insert(pos) {
-                    let field = self.vault.map[index(pos)];
-                    match field {
-                        b'#' => continue,
-                        b'.' | b'@' => {
-                            self.q.push_back((pos, dist + 1, doors));
-                        }
-                        a if (a as char).is_lowercase() => {
-                            self.q.push_back((pos, dist + 1, doors));
-                        }
-                        a if (a as char).is_uppercase() => {
-                            let door = 1 << (a - b'A');
-                            self.q.push_back((pos, dist + 1, doors | door))
-                        }
-                        _ => unreachable!(),
-                    }
+            for next in self.vault.neighbors(pos) {
+                if self.seen.insert(next) {
+                    let tile = self.vault.get(next);
+                    let door = if tile.is_ascii_uppercase() {
+                        1 << (tile - b'A')
+                    } else {
+                        0
+                    };
+                    self.q.push_back((next, dist + 1, doors | door, keys));
                 }
             }
         }
@@ -82,157 +193,324 @@ impl<'a> PathFinder<'a> {
     }
 }
 
-fn shortest_path_len(vault: &Vault) -> Option<usize> {
-    let width = vault.width;
-    let coord = |i| ((i % width) as u8, (i / width) as u8);
-
-    let orig = vault.map.iter().position(|&v| v == b'@').map(coord)?;
-    let keys: HashMap<_, _> = vault
-        .map
-        .iter()
-        .enumerate()
-        .filter(|(_, &v)| (v as char).is_lowercase())
-        .map(|(i, &v)| (coord(i), 1_u32 << (v - b'a')))
-        .collect();
-
-    // precompute all distances from each key to each key with doors on the way
-    let mut path_finder = PathFinder::new(vault);
-    let paths: HashMap<_, _> = keys
-        .keys()
-        .chain([orig].iter())
-        .map(|&from| (from, path_finder.bfs(from, &keys)))
-        .collect();
-
-    let keys_bitset = (1 << keys.len()) - 1;
-    dijkstra([orig], &paths, keys_bitset)
+/// Paths between the entrances and all keys, computed once per vault.
+#[derive(Debug, Clone)]
+pub struct KeyGraph {
+    /// Entrances followed by keys.
+    nodes: Vec<Coord>,
+    num_robots: usize,
+    /// Paths from each node, with destinations as node indices.
+    edges: Vec<Vec<(usize, Path)>>,
+    /// Distances between all nodes ignoring doors, if connected.
+    dists: Vec<Vec<Option<usize>>>,
+    /// Key at each node; none at the entrances.
+    node_keys: Vec<u32>,
+    all_keys: u32,
 }
 
-fn shortest_path_len4(mut vault: Vault) -> Option<usize> {
-    let width = vault.width;
-    let coord = |i| ((i % width) as u8, (i / width) as u8);
-    let index = |(x, y)| x as usize + width * y as usize;
-
-    let orig = vault.map.iter().position(|&v| v == b'@').map(coord)?;
-    let keys: HashMap<_, _> = vault
-        .map
-        .iter()
-        .enumerate()
-        .filter(|(_, &v)| (v as char).is_lowercase())
-        .map(|(i, &v)| (coord(i), 1_u32 << (v - b'a')))
-        .collect();
-
-    // add 4 robots and walls around them
-    let (x, y) = orig;
-    for &pos in [(x, y), (x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)].iter() {
-        vault.map[index(pos)] = b'#';
+impl KeyGraph {
+    pub fn new(vault: &Vault) -> Self {
+        let entrances = vault.entrances();
+        let num_robots = entrances.len();
+        let nodes: Vec<Coord> = entrances.into_iter().chain(vault.keys()).collect();
+        let index: HashMap<Coord, usize> = nodes.iter().enumerate().map(|(i, &p)| (p, i)).collect();
+
+        let mut path_finder = PathFinder::new(vault);
+        let edges: Vec<Vec<(usize, Path)>> = nodes
+            .iter()
+            .map(|&from| {
+                path_finder
+                    .bfs(from)
+                    .into_iter()
+                    .map(|path| (index[&path.dest], path))
+                    .collect()
+            })
+            .collect();
+        let mut dists = vec![vec![None; nodes.len()]; nodes.len()];
+        for (from, paths) in edges.iter().enumerate() {
+            dists[from][from] = Some(0);
+            for &(to, ref path) in paths {
+                dists[from][to] = Some(path.dist);
+            }
+        }
+        let node_keys: Vec<u32> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, &pos)| {
+                if i < num_robots {
+                    0
+                } else {
+                    1 << (vault.get(pos) - b'a')
+                }
+            })
+            .collect();
+        let all_keys = node_keys.iter().fold(0, |keys, key| keys | key);
+
+        Self {
+            nodes,
+            num_robots,
+            edges,
+            dists,
+            node_keys,
+            all_keys,
+        }
+    }
+
+    pub fn num_robots(&self) -> usize {
+        self.num_robots
+    }
+
+    /// Paths to keys from the entrance or key at the given position.
+    pub fn paths(&self, from: Coord) -> Option<impl Iterator<Item = &Path>> {
+        let node = self.nodes.iter().position(|&pos| pos == from)?;
+        Some(self.edges[node].iter().map(|(_, path)| path))
     }
-    let origins = [
-        (x - 1, y - 1),
-        (x + 1, y - 1),
-        (x + 1, y + 1),
-        (x - 1, y + 1),
-    ];
-
-    // precompute all distances from each key to each key with doors on the way
-    let mut path_finder = PathFinder::new(&vault);
-    let paths: HashMap<_, _> = keys
-        .keys()
-        .chain(origins.iter())
-        .map(|&from| (from, path_finder.bfs(from, &keys)))
-        .collect();
-
-    let keys_bitset = (1 << keys.len()) - 1;
-    dijkstra(origins, &paths, keys_bitset)
 }
 
-// Length of the shortest path to collect all keys and open all doors.
-//
-// We run Dijkstra on the set of all precomputed paths from origins + keys to all keys and check if
-// the path if traversable by looking at the doors along it and the keys we've already collected.
-fn dijkstra<Ar>(
-    orig: Ar,
-    paths: &HashMap<Coord, Vec<(Coord, usize, u32, u32)>>,
-    keys: u32,
-) -> Option<usize>
-where
-    Ar: AsRef<[Coord]> + AsMut<[Coord]> + Copy + Eq + Ord + std::hash::Hash,
-{
-    let mut heap = BinaryHeap::new();
-    heap.push(Reverse((0, orig, 0)));
+/// Length of the shortest way to collect all keys and the keys in the order they are collected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    pub len: usize,
+    pub order: String,
+}
 
-    let mut distances = HashMap::new();
-    distances.insert((orig, 0), 0);
+/// Robots at graph nodes and the keys collected so far.
+type State = (Vec<usize>, u32);
+
+/// Best distance to each state and the previous state with the key collected since.
+type Distances = HashMap<State, (usize, Option<(State, u32)>)>;
+
+/// Lower bound on the remaining distance used to guide the search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heuristic {
+    /// Plain Dijkstra.
+    None,
+    /// The farthest remaining key from its closest robot, which has to be reached at least.
+    MaxDistance,
+    /// Weight of the minimum spanning tree over the remaining keys and all robots merged into a
+    /// single node, since the walks of the robots form a spanning forest rooted in the robots.
+    SpanningTree,
+}
 
-    while let Some(Reverse((dist, positions, collected_keys))) = heap.pop() {
-        if collected_keys == keys {
-            return Some(dist);
+impl Heuristic {
+    fn estimate(self, graph: &KeyGraph, (robots, collected): &State) -> usize {
+        let remaining: Vec<usize> = (graph.num_robots..graph.nodes.len())
+            .filter(|&node| graph.node_keys[node] & collected == 0)
+            .collect();
+        // distance of each remaining key to the closest robot
+        let to_robots = remaining.iter().map(|&key| {
+            robots
+                .iter()
+                .filter_map(|&robot| graph.dists[robot][key])
+                .min()
+        });
+        match self {
+            Heuristic::None => 0,
+            Heuristic::MaxDistance => to_robots.flatten().max().unwrap_or(0),
+            Heuristic::SpanningTree => {
+                // Prim's algorithm growing from the robots; unreachable keys are skipped
+                let mut best: Vec<Option<usize>> = to_robots.collect();
+                let mut in_tree = vec![false; remaining.len()];
+                let mut weight = 0;
+                loop {
+                    let next = (0..remaining.len())
+                        .filter(|&i| !in_tree[i])
+                        .filter_map(|i| best[i].map(|d| (d, i)))
+                        .min();
+                    let (dist, i) = match next {
+                        Some(next) => next,
+                        None => break,
+                    };
+                    weight += dist;
+                    in_tree[i] = true;
+                    for (j, &key) in remaining.iter().enumerate() {
+                        if let Some(d) = graph.dists[remaining[i]][key] {
+                            if best[j].is_none_or(|b| d < b) {
+                                best[j] = Some(d);
+                            }
+                        }
+                    }
+                }
+                weight
+            }
         }
+    }
+}
 
-        for (i, pos) in positions.as_ref().iter().enumerate() {
-            let reachabled_keys =
-                paths[&pos]
-                    .iter()
-                    .copied()
-                    .filter(|&(_dest, _dist, key, doors)| {
-                        collected_keys & key == 0 && !collected_keys & doors == 0
-                    });
+/// How to search the key states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    pub heuristic: Heuristic,
+    /// Skips states whose robots already stood at the same positions with a superset of the keys
+    /// after at most the same distance.
+    pub prune: bool,
+}
 
-            for (dest, key_dist, key, _) in reachabled_keys {
-                let collected_keys = collected_keys | key;
-                let dist = dist + key_dist;
-                let mut positions = positions;
-                positions.as_mut()[i] = dest;
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            heuristic: Heuristic::SpanningTree,
+            prune: true,
+        }
+    }
+}
+
+/// Number of states handled by a search.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// States taken from the queue and expanded.
+    pub expanded: usize,
+    /// States added to the queue.
+    pub queued: usize,
+    /// States skipped because they were dominated.
+    pub pruned: usize,
+}
+
+pub fn shortest_path(vault: &Vault) -> Option<Solution> {
+    search(&KeyGraph::new(vault), Options::default()).0
+}
+
+/// Runs A* on the states reached by moving one robot to a key along a precomputed path.
+///
+/// A path is traversable if all doors on it are open, and it must not pass a key which is not
+/// collected yet, since that key would be collected first. With `Heuristic::None`, this is
+/// Dijkstra's algorithm.
+pub fn search(graph: &KeyGraph, options: Options) -> (Option<Solution>, Stats) {
+    let mut stats = Stats::default();
+    if graph.num_robots == 0 {
+        return (None, stats);
+    }
+    let start: State = ((0..graph.num_robots).collect(), 0);
+
+    let mut heap = BinaryHeap::new();
+    heap.push(Reverse((
+        options.heuristic.estimate(graph, &start),
+        0,
+        start.clone(),
+    )));
+    stats.queued += 1;
+
+    let mut distances = Distances::new();
+    distances.insert(start, (0, None));
+    // keys and distances of the states seen for each robot position
+    let mut seen: HashMap<Vec<usize>, Vec<(u32, usize)>> = HashMap::new();
+
+    while let Some(Reverse((_, dist, state))) = heap.pop() {
+        if dist > distances[&state].0 {
+            continue;
+        }
+        stats.expanded += 1;
+        let (robots, collected) = &state;
+        if *collected == graph.all_keys {
+            let solution = Solution {
+                len: dist,
+                order: order(&distances, state),
+            };
+            return (Some(solution), stats);
+        }
 
-                match distances.entry((positions, collected_keys)) {
+        for (i, &node) in robots.iter().enumerate() {
+            let reachable_keys = graph.edges[node].iter().filter(|(_, path)| {
+                collected & path.key == 0
+                    && path.doors & !collected == 0
+                    && path.keys & !collected == 0
+            });
+            for &(dest, ref path) in reachable_keys {
+                let mut robots = robots.clone();
+                robots[i] = dest;
+                let keys = collected | path.key;
+                let dist = dist + path.dist;
+
+                let dominated = options.prune
+                    && seen.get(&robots).is_some_and(|states| {
+                        states
+                            .iter()
+                            .any(|&(k, d)| k & keys == keys && d <= dist && (k, d) != (keys, dist))
+                    });
+                if dominated {
+                    stats.pruned += 1;
+                    continue;
+                }
+
+                let next = (robots, keys);
+                let prev = Some((state.clone(), path.key));
+                let improved = match distances.entry(next.clone()) {
                     Entry::Vacant(entry) => {
-                        entry.insert(dist);
-                        heap.push(Reverse((dist, positions, collected_keys)));
+                        entry.insert((dist, prev));
+                        true
+                    }
+                    Entry::Occupied(mut entry) if dist < entry.get().0 => {
+                        entry.insert((dist, prev));
+                        true
                     }
-                    Entry::Occupied(mut entry) if dist < *entry.get() => {
-                        entry.insert(dist);
-                        heap.push(Reverse((dist, positions, collected_keys)));
+                    _ => false,
+                };
+                if improved {
+                    if options.prune {
+                        seen.entry(next.0.clone()).or_default().push((keys, dist));
                     }
-                    _ => {}
+                    let estimate = dist + options.heuristic.estimate(graph, &next);
+                    heap.push(Reverse((estimate, dist, next)));
+                    stats.queued += 1;
                 }
             }
         }
     }
 
-    None
+    (None, stats)
+}
+
+fn order(distances: &Distances, mut state: State) -> String {
+    let mut keys = Vec::new();
+    while let Some((prev, key)) = &distances[&state].1 {
+        keys.push((b'a' + key.trailing_zeros() as u8) as char);
+        state = prev.clone();
+    }
+    keys.iter().rev().collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn shortest(input: &str) -> Option<Solution> {
+        shortest_path(&Vault::parse(input).unwrap())
+    }
+
     #[test]
-    fn test_shortest_path_len() {
-        let vault = parse(
+    fn test_shortest_path() {
+        let solution = shortest(
             r#"#########
 #b.A.@.a#
 #########"#,
-        );
-        assert_eq!(shortest_path_len(&vault), Some(8));
+        )
+        .unwrap();
+        assert_eq!(solution.len, 8);
+        assert_eq!(solution.order, "ab");
 
-        let vault = parse(
+        let solution = shortest(
             r#"########################
 #f.D.E.e.C.b.A.@.a.B.c.#
 ######################.#
 #d.....................#
 ########################"#,
-        );
-        assert_eq!(shortest_path_len(&vault), Some(86));
+        )
+        .unwrap();
+        assert_eq!(solution.len, 86);
+        assert_eq!(solution.order, "abcdef");
 
-        let vault = parse(
+        let solution = shortest(
             r#"########################
 #...............b.C.D.f#
 #.######################
 #.....@.a.B.c.d.A.e.F.g#
 ########################"#,
-        );
-        assert_eq!(shortest_path_len(&vault), Some(132));
+        )
+        .unwrap();
+        assert_eq!(solution.len, 132);
+        assert_eq!(solution.order, "bacdfeg");
 
-        let vault = parse(
+        let solution = shortest(
             r#"#################
 #i.G..c...e..H.p#
 ########.########
@@ -242,23 +520,84 @@ mod tests {
 ########.########
 #l.F..d...h..C.m#
 #################"#,
-        );
-        assert_eq!(shortest_path_len(&vault), Some(136));
+        )
+        .unwrap();
+        assert_eq!(solution.len, 136);
 
-        let vault = parse(
+        let solution = shortest(
             r#"########################
 #@..............ac.GI.b#
 ###d#e#f################
 ###A#B#C################
 ###g#h#i################
 ########################"#,
+        )
+        .unwrap();
+        assert_eq!(solution.len, 81);
+    }
+
+    #[test]
+    fn test_search_options() {
+        let vault = Vault::parse(
+            r#"#################
+#i.G..c...e..H.p#
+########.########
+#j.A..b...f..D.o#
+########@########
+#k.E..a...g..B.n#
+########.########
+#l.F..d...h..C.m#
+#################"#,
+        )
+        .unwrap();
+        let graph = KeyGraph::new(&vault);
+        let dijkstra = Options {
+            heuristic: Heuristic::None,
+            prune: false,
+        };
+        let (solution, plain) = search(&graph, dijkstra);
+        assert_eq!(solution.unwrap().len, 136);
+
+        for &heuristic in &[
+            Heuristic::None,
+            Heuristic::MaxDistance,
+            Heuristic::SpanningTree,
+        ] {
+            for &prune in &[false, true] {
+                let (solution, stats) = search(&graph, Options { heuristic, prune });
+                assert_eq!(solution.unwrap().len, 136);
+                assert!(stats.expanded <= plain.expanded);
+                assert_eq!(stats.pruned > 0, prune);
+            }
+        }
+        let (_, informed) = search(&graph, Options::default());
+        assert!(informed.expanded < plain.expanded);
+
+        // the estimates never exceed the true distance from the start
+        let start = ((0..graph.num_robots()).collect(), 0);
+        assert_eq!(Heuristic::MaxDistance.estimate(&graph, &start), 10);
+        // the two middle rows hang directly at the robot
+        assert_eq!(
+            Heuristic::SpanningTree.estimate(&graph, &start),
+            19 + 16 + 16 + 19
         );
-        assert_eq!(shortest_path_len(&vault), Some(81));
     }
 
     #[test]
-    fn test_shortest_path_len4() {
-        let vault = parse(
+    fn test_four_robots() {
+        let mut vault = Vault::parse(
+            r#"#######
+#a.#Cd#
+##...##
+##...##
+##...##
+#cB#Ab#
+#######"#,
+        )
+        .unwrap();
+        assert!(vault.split_entrance().is_err());
+
+        let mut vault = Vault::parse(
             r#"#######
 #a.#Cd#
 ##...##
@@ -266,7 +605,36 @@ mod tests {
 ##...##
 #cB#Ab#
 #######"#,
+        )
+        .unwrap();
+        vault.split_entrance().unwrap();
+        assert_eq!(vault.entrances().len(), 4);
+        let solution = shortest_path(&vault).unwrap();
+        assert_eq!(solution.len, 8);
+        assert_eq!(solution.order, "abcd");
+        assert!(vault.split_entrance().is_err());
+    }
+
+    #[test]
+    fn test_any_number_of_robots() {
+        // each robot needs the key of the other one
+        let solution = shortest("#########\n#aB@#@.b#\n#########").unwrap();
+        assert_eq!(
+            solution,
+            Solution {
+                len: 4,
+                order: "ba".into()
+            }
         );
-        assert_eq!(shortest_path_len4(vault), Some(8));
+        assert_eq!(shortest("##########\n#a.B@#@Ab#\n##########"), None);
+
+        let vault = Vault::parse("#######\n#@a#@b#\n#######").unwrap();
+        let graph = KeyGraph::new(&vault);
+        assert_eq!(graph.num_robots(), 2);
+        assert_eq!(graph.paths((1, 1)).unwrap().count(), 1);
+        assert!(graph.paths((3, 1)).is_none());
+
+        assert!(Vault::parse("#a#\n#a#").is_err());
+        assert!(Vault::parse("#a#\n#").is_err());
     }
 }
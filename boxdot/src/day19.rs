@@ -1,72 +1,303 @@
 use crate::day09::{execute, parse, Memory};
-use itertools::iproduct;
+use crate::tui::Canvas;
+use std::collections::HashMap;
 
 type Error = Box<dyn std::error::Error>;
 type Result<T> = std::result::Result<T, Error>;
 type Coord = (usize, usize);
 
+/// Extra cells searched for the beam beyond the expected position of its lower edge.
+const SEARCH_MARGIN: usize = 10;
+/// Number of rows after which the search for a fitting rectangle gives up.
+const MAX_ROWS: usize = 100_000;
+
 pub fn solve(input: &str) -> Result<(usize, usize)> {
-    let mem = parse(input);
-    let shape: Result<Vec<bool>> = iproduct!(0..50, 0..50)
-        .map(|pos| scan(mem.clone(), pos))
-        .collect();
-    let shape = shape?;
-    println!("{}", to_string(&shape, 50));
+    let mut beam = Beam::new(Drone::new(parse(input)));
+    let shape = beam.area(50, 50)?;
+    println!(
+        "{}",
+        shape.render(&|&pulled: &bool| if pulled { '#' } else { '.' })
+    );
 
-    let part1 = shape.iter().filter(|&&x| x).count();
+    let part1 = beam.count(50, 50)?;
 
-    let (x, y) = find_fitting_shape(&mem);
+    let (x, y) = beam.fit(100, 100)?;
     let part2 = x * 10000 + y;
+    println!("deployed {} drones", beam.probe().invocations());
 
     Ok((part1, part2))
 }
 
-// false - not pulled (i.e. stationary), true - pulled
-fn scan(mut mem: Memory, pos: Coord) -> Result<bool> {
-    let mut index = 0;
-    let input = [pos.0 as i64, pos.1 as i64];
-
-    let mut retval = false;
-    let mut ip = Some(0);
-    while let Some(next_ip) = ip {
-        ip = execute(
-            &mut mem,
-            next_ip,
-            || {
-                let value = input[index];
-                index += 1;
-                value
-            },
-            |value| retval = value == 1,
-        )?;
-    }
-
-    Ok(retval)
+/// Scans the rectangle of the given size at the emitter for cells pulled by the beam.
+pub fn scan_area(mem: &Memory, width: usize, height: usize) -> Result<Canvas<bool>> {
+    Beam::new(Drone::new(mem.clone())).area(width, height)
+}
+
+/// Tells whether a position is pulled by the beam.
+pub trait Probe {
+    fn pulled(&mut self, pos: Coord) -> Result<bool>;
+}
+
+/// Deploys a drone with a fresh copy of the Intcode program for each query.
+pub struct Drone {
+    program: Memory,
+    mem: Memory,
+    invocations: usize,
 }
 
-fn find_fitting_shape(mem: &Memory) -> (usize, usize) {
-    let mut x = 0;
-    for y in 100.. {
-        x += (x..)
-            .position(|x| scan(mem.clone(), (x, y)).unwrap())
-            .unwrap();
-        let corner = (x + 100 - 1, y - 100 + 1);
-        if scan(mem.clone(), corner).unwrap() {
-            return (x, y - 100 + 1);
+impl Drone {
+    pub fn new(program: Memory) -> Self {
+        Self {
+            mem: program.clone(),
+            program,
+            invocations: 0,
         }
     }
-    unreachable!();
+
+    /// Number of times the program was run.
+    pub fn invocations(&self) -> usize {
+        self.invocations
+    }
 }
 
-fn to_string(shape: &[bool], width: usize) -> String {
-    use itertools::Itertools;
-    shape
-        .chunks(width)
-        .map(|l| {
-            l.iter()
-                .map(|&pulled| if pulled { '#' } else { '.' })
-                .collect::<String>()
-        })
-        .intersperse("\n".into())
-        .collect()
+impl Probe for Drone {
+    fn pulled(&mut self, pos: Coord) -> Result<bool> {
+        self.invocations += 1;
+        self.mem.restore(&self.program);
+
+        let mut index = 0;
+        let input = [pos.0 as i64, pos.1 as i64];
+        let mut pulled = None;
+        let mut ip = Some(0);
+        while let Some(next_ip) = ip {
+            ip = execute(
+                &mut self.mem,
+                next_ip,
+                || {
+                    let value = input.get(index).copied().unwrap_or(0);
+                    index += 1;
+                    value
+                },
+                |value| pulled = Some(value == 1),
+            )?;
+        }
+        pulled.ok_or_else(|| format!("no output for {:?}", pos).into())
+    }
+}
+
+/// Beam coming from the emitter at the origin, traced by its edges.
+///
+/// The beam is assumed to be a cone: every row holds at most one run of pulled cells, and both
+/// ends of the run never move left from one row to the next. Then each row costs only a few
+/// probes, starting at the edges of the previous row.
+pub struct Beam<P> {
+    probe: P,
+    cache: HashMap<Coord, bool>,
+    /// Pulled cells of the traced rows as start and end (exclusive).
+    rows: Vec<Option<(usize, usize)>>,
+    /// Last row with pulled cells.
+    last: Option<(usize, (usize, usize))>,
+    queries: usize,
+}
+
+impl<P: Probe> Beam<P> {
+    pub fn new(probe: P) -> Self {
+        Self {
+            probe,
+            cache: HashMap::new(),
+            rows: Vec::new(),
+            last: None,
+            queries: 0,
+        }
+    }
+
+    pub fn probe(&self) -> &P {
+        &self.probe
+    }
+
+    /// Number of positions asked for, including the answers from the cache.
+    pub fn queries(&self) -> usize {
+        self.queries
+    }
+
+    /// Number of positions which had to be probed.
+    pub fn probed(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn pulled(&mut self, pos: Coord) -> Result<bool> {
+        self.queries += 1;
+        if let Some(&pulled) = self.cache.get(&pos) {
+            return Ok(pulled);
+        }
+        let pulled = self.probe.pulled(pos)?;
+        self.cache.insert(pos, pulled);
+        Ok(pulled)
+    }
+
+    /// Pulled cells of the row as start and end (exclusive), if any.
+    pub fn row(&mut self, y: usize) -> Result<Option<(usize, usize)>> {
+        while self.rows.len() <= y {
+            let row = self.trace(self.rows.len())?;
+            self.rows.push(row);
+        }
+        Ok(self.rows[y])
+    }
+
+    fn trace(&mut self, y: usize) -> Result<Option<(usize, usize)>> {
+        // the lower edge is expected close to the previous one scaled to this row, but the
+        // emitter alone tells nothing about the direction of the beam
+        let (mut x, limit) = match self.last {
+            Some((prev_y, (start, end))) if prev_y > 0 => {
+                (start, end * (y + 1) / (prev_y + 1) + SEARCH_MARGIN)
+            }
+            _ => (0, SEARCH_MARGIN * (y + 1)),
+        };
+        while x < limit && !self.pulled((x, y))? {
+            x += 1;
+        }
+        if x == limit {
+            return Ok(None);
+        }
+
+        // all cells up to the previous upper edge are pulled as well
+        let start = x;
+        let mut end = self.last.map_or(start, |(_, (_, end))| end).max(start + 1);
+        while self.pulled((end, y))? {
+            end += 1;
+        }
+        self.last = Some((y, (start, end)));
+        Ok(Some((start, end)))
+    }
+
+    /// Number of pulled cells in the rectangle of the given size at the emitter.
+    pub fn count(&mut self, width: usize, height: usize) -> Result<usize> {
+        let mut count = 0;
+        for y in 0..height {
+            if let Some((start, end)) = self.row(y)? {
+                count += end.min(width).saturating_sub(start);
+            }
+        }
+        Ok(count)
+    }
+
+    /// Cells in the rectangle of the given size at the emitter.
+    pub fn area(&mut self, width: usize, height: usize) -> Result<Canvas<bool>> {
+        let mut canvas = Canvas::new();
+        for y in 0..height {
+            let row = self.row(y)?;
+            for x in 0..width {
+                let pulled = row.is_some_and(|(start, end)| start <= x && x < end);
+                canvas.set((x as i64, y as i64), pulled);
+            }
+        }
+        Ok(canvas)
+    }
+
+    /// Top left corner of the rectangle of the given size closest to the emitter which lies
+    /// completely inside the beam.
+    pub fn fit(&mut self, width: usize, height: usize) -> Result<Coord> {
+        if width == 0 || height == 0 {
+            return Ok((0, 0));
+        }
+        // the bottom left corner lies on the lower edge, and the top row must reach far enough
+        for y in height - 1..MAX_ROWS {
+            let (start, end) = match self.row(y)? {
+                Some(row) => row,
+                None => continue,
+            };
+            if end - start < width {
+                continue;
+            }
+            let top = y + 1 - height;
+            if let Some((top_start, top_end)) = self.row(top)? {
+                if top_start <= start && start + width <= top_end {
+                    return Ok((start, top));
+                }
+            }
+        }
+        Err(format!(
+            "no {}x{} rectangle in the first {} rows",
+            width, height, MAX_ROWS
+        )
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Beam between the slopes 3/4 and 5/4 counting the probes.
+    #[derive(Default)]
+    struct Cone {
+        probes: usize,
+    }
+
+    impl Probe for Cone {
+        fn pulled(&mut self, (x, y): Coord) -> Result<bool> {
+            self.probes += 1;
+            Ok(3 * y <= 4 * x && 4 * x <= 5 * y || (x, y) == (0, 0))
+        }
+    }
+
+    /// Narrow beam between the slopes 73/10 and 76/10, which misses the first row.
+    struct Narrow;
+
+    impl Probe for Narrow {
+        fn pulled(&mut self, (x, y): Coord) -> Result<bool> {
+            Ok(73 * y <= 10 * x && 10 * x <= 76 * y || (x, y) == (0, 0))
+        }
+    }
+
+    #[test]
+    fn test_rows_and_count() {
+        let mut beam = Beam::new(Cone::default());
+        assert_eq!(beam.row(0).unwrap(), Some((0, 1)));
+        assert_eq!(beam.row(4).unwrap(), Some((3, 6)));
+        assert_eq!(beam.row(40).unwrap(), Some((30, 51)));
+
+        let mut cone = Cone::default();
+        let expected = (0..50)
+            .flat_map(|y| (0..50).map(move |x| (x, y)))
+            .filter(|&pos| cone.pulled(pos).unwrap())
+            .count();
+        assert_eq!(beam.count(50, 50).unwrap(), expected);
+        let area = beam.area(50, 50).unwrap();
+        assert_eq!(area.values().filter(|&&pulled| pulled).count(), expected);
+
+        // tracing the edges needs a fraction of the probes of a full scan
+        assert_eq!(beam.probe().probes, beam.probed());
+        assert!(beam.probed() < 2500 / 10);
+        let (queries, probed) = (beam.queries(), beam.probed());
+        assert!(beam.pulled((3, 4)).unwrap());
+        assert_eq!((beam.queries(), beam.probed()), (queries + 1, probed));
+    }
+
+    #[test]
+    fn test_fit() {
+        let mut beam = Beam::new(Cone::default());
+        let (x, y) = beam.fit(10, 10).unwrap();
+        let mut cone = Cone::default();
+        for (dx, dy) in [(0, 0), (9, 0), (0, 9), (9, 9)].iter() {
+            assert!(cone.pulled((x + dx, y + dy)).unwrap());
+        }
+        // one row higher, no rectangle fits
+        assert!((0..=x + 10).all(|x| {
+            (0..10).any(|dy| (0..10).any(|dx| !cone.pulled((x + dx, y - 1 + dy)).unwrap()))
+        }));
+        assert_eq!(beam.fit(0, 3).unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn test_empty_rows() {
+        let mut beam = Beam::new(Narrow);
+        assert_eq!(beam.row(1).unwrap(), None);
+        assert_eq!(beam.row(2).unwrap(), Some((15, 16)));
+        assert_eq!(beam.row(20).unwrap(), Some((146, 153)));
+        let (x, y) = beam.fit(3, 3).unwrap();
+        for (dx, dy) in [(0, 0), (2, 0), (0, 2), (2, 2)].iter() {
+            assert!(Narrow.pulled((x + dx, y + dy)).unwrap());
+        }
+    }
 }
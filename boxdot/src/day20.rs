@@ -1,221 +1,504 @@
-use std::cmp::Reverse;
-use std::collections::hash_map::Entry;
-use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
-
-pub fn solve(input: &str) -> Option<(usize, usize)> {
-    let map = parse(input);
-    let part1 = shortest_path_len(&map)?;
-    let part2 = shortest_path_len_with_levels(&map)?;
-    Some((part1, part2))
+use std::collections::{HashMap, HashSet, VecDeque};
+
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
+
+pub fn solve(input: &str) -> Result<(usize, usize)> {
+    let levels = [Maze::parse(input)?];
+    let plain = shortest_path(&levels, &Rules::default())?.ok_or("no path from AA to ZZ")?;
+    let recursive = Rules {
+        recursive: true,
+        ..Rules::default()
+    };
+    let recursive = shortest_path(&levels, &recursive)?.ok_or("no path through the levels")?;
+    Ok((plain.len(), recursive.len()))
+}
+
+pub type Coord = (usize, usize);
+
+const START: &str = "AA";
+const END: &str = "ZZ";
+/// Multiple of the tiles on all levels a path may need, after visiting which the search gives up.
+const MAX_TILES_FACTOR: usize = 2;
+
+/// Side of the donut on which a portal lies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Inner,
+    Outer,
 }
 
-type Coord = (usize, usize);
-type Portal = (char, char);
+impl Side {
+    pub fn opposite(self) -> Self {
+        match self {
+            Side::Inner => Side::Outer,
+            Side::Outer => Side::Inner,
+        }
+    }
+}
+
+/// Open tile next to a two-letter label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Portal {
+    pub label: String,
+    pub side: Side,
+}
 
-#[derive(Debug)]
-struct Vault {
+#[derive(Debug, Clone)]
+pub struct Maze {
     map: Vec<Vec<u8>>,
+    /// Smallest and largest coordinates of walls and open tiles.
+    bounds: (Coord, Coord),
+    portals: HashMap<Coord, Portal>,
+    /// Tile of each portal by label and side.
+    tiles: HashMap<(String, Side), Coord>,
 }
 
-impl Vault {
-    fn iter(&self) -> impl Iterator<Item = (Coord, char)> + Clone + '_ {
+impl Maze {
+    pub fn parse(input: &str) -> Result<Self> {
+        let map: Vec<Vec<u8>> = input.lines().map(|l| l.bytes().collect()).collect();
+        let mut maze = Self {
+            map,
+            bounds: ((usize::MAX, usize::MAX), (0, 0)),
+            portals: HashMap::new(),
+            tiles: HashMap::new(),
+        };
+
+        maze.bounds = maze
+            .iter()
+            .filter(|(_, c)| matches!(c, b'#' | b'.'))
+            .fold(maze.bounds, |((x0, y0), (x1, y1)), ((x, y), _)| {
+                ((x0.min(x), y0.min(y)), (x1.max(x), y1.max(y)))
+            });
+
+        let letters: Vec<(Coord, u8)> = maze
+            .iter()
+            .filter(|(_, c)| c.is_ascii_uppercase())
+            .collect();
+        for ((x, y), c) in letters {
+            // labels read left to right or top to bottom
+            for &(dx, dy) in &[(1, 0), (0, 1)] {
+                let second = (x + dx, y + dy);
+                let d = match maze.get(second) {
+                    Some(d) if d.is_ascii_uppercase() => d,
+                    _ => continue,
+                };
+                let after = (x + 2 * dx, y + 2 * dy);
+                let before = (x.wrapping_sub(dx), y.wrapping_sub(dy));
+                let pos = if maze.get(after) == Some(b'.') {
+                    after
+                } else if maze.get(before) == Some(b'.') {
+                    before
+                } else {
+                    return Err(format!("label at {:?} is not next to an open tile", (x, y)).into());
+                };
+                let label = format!("{}{}", c as char, d as char);
+                let side = if maze.is_outer(pos) {
+                    Side::Outer
+                } else {
+                    Side::Inner
+                };
+                if maze.tiles.insert((label.clone(), side), pos).is_some() {
+                    return Err(format!("duplicate {:?} portal {}", side, label).into());
+                }
+                maze.portals.insert(pos, Portal { label, side });
+            }
+        }
+        Ok(maze)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Coord, u8)> + '_ {
         self.map
             .iter()
             .enumerate()
-            .flat_map(move |(y, l)| l.iter().enumerate().map(move |(x, c)| ((x, y), *c as char)))
+            .flat_map(move |(y, l)| l.iter().enumerate().map(move |(x, c)| ((x, y), *c)))
+    }
+
+    fn get(&self, (x, y): Coord) -> Option<u8> {
+        Some(*self.map.get(y)?.get(x)?)
+    }
+
+    fn open_tiles(&self) -> usize {
+        self.iter().filter(|&(_, c)| c == b'.').count()
     }
 
-    fn get(&self, (x, y): Coord) -> Option<char> {
-        Some(*self.map.get(y)?.get(x)? as char)
+    fn is_open(&self, pos: Coord) -> bool {
+        self.get(pos) == Some(b'.')
     }
 
     fn is_outer(&self, (x, y): Coord) -> bool {
-        let width = self.map[0].len();
-        let height = self.map.len();
-        x == 2 || x + 3 == width || y == 2 || y + 3 == height
+        let ((x0, y0), (x1, y1)) = self.bounds;
+        x == x0 || x == x1 || y == y0 || y == y1
+    }
+
+    pub fn portal(&self, pos: Coord) -> Option<&Portal> {
+        self.portals.get(&pos)
+    }
+
+    /// Tile of the portal with the given label on the given side.
+    pub fn tile(&self, label: &str, side: Side) -> Option<Coord> {
+        self.tiles.get(&(label.to_string(), side)).copied()
+    }
+
+    fn endpoint(&self, label: &str) -> Option<Coord> {
+        self.tile(label, Side::Outer)
+            .or_else(|| self.tile(label, Side::Inner))
     }
 }
 
-fn parse(input: &str) -> Vault {
-    let map = input.lines().map(|l| l.bytes().collect()).collect();
-    Vault { map }
+/// How the portals connect the tiles.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rules {
+    /// Inner portals lead one level down and outer portals one level up, where the outer portals
+    /// of the outermost level are walls; otherwise all portals stay on the outermost level.
+    pub recursive: bool,
+    /// Deepest level which may be entered, if any.
+    pub max_depth: Option<usize>,
+    /// Portals which can only be entered from the given side.
+    pub one_way: HashMap<String, Side>,
 }
 
-fn portals(vault: &Vault) -> HashMap<Coord, Portal> {
-    let letters = vault.iter().filter(|&(_, c)| c.is_ascii_uppercase());
-    let horiz = letters.clone().filter_map(|((x, y), c)| {
-        vault
-            .get((x + 1, y))
-            .filter(char::is_ascii_uppercase)
-            .map(|d| {
-                if vault.get((x + 2, y)) == Some('.') {
-                    ((x + 2, y), (c, d))
-                } else if vault.get((x.saturating_sub(1), y)) == Some('.') {
-                    ((x - 1, y), (c, d))
-                } else {
-                    panic!("invalid portal");
-                }
-            })
-    });
-    let vert = letters.clone().filter_map(|((x, y), c)| {
-        vault
-            .get((x, y + 1))
-            .filter(char::is_ascii_uppercase)
-            .map(|d| {
-                if vault.get((x, y + 2)) == Some('.') {
-                    ((x, y + 2), (c, d))
-                } else if vault.get((x, y.saturating_sub(1))) == Some('.') {
-                    ((x, y - 1), (c, d))
+/// Tile on a level, where 0 is the outermost level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Step {
+    pub level: usize,
+    pub pos: Coord,
+}
+
+/// Tiles from AA to ZZ, including both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub steps: Vec<Step>,
+}
+
+impl Route {
+    /// Number of moves, where going through a portal is one move.
+    pub fn len(&self) -> usize {
+        self.steps.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn max_level(&self) -> usize {
+        self.steps.iter().map(|s| s.level).max().unwrap_or(0)
+    }
+
+    /// Portals taken as the label with the levels before and after.
+    pub fn transitions<'a>(&self, levels: &'a [Maze]) -> Vec<(&'a str, usize, usize)> {
+        self.steps
+            .windows(2)
+            .filter_map(|w| {
+                let maze = level_map(levels, w[0].level);
+                let portal = maze.portal(w[0].pos)?;
+                let (a, b) = (w[0].pos, w[1].pos);
+                let adjacent = a.0.abs_diff(b.0) + a.1.abs_diff(b.1) == 1;
+                if adjacent && w[0].level == w[1].level {
+                    None
                 } else {
-                    panic!("invalid portal");
+                    Some((portal.label.as_str(), w[0].level, w[1].level))
                 }
             })
-    });
-    horiz.chain(vert).collect()
+            .collect()
+    }
+}
+
+/// Map of a level; the last map repeats for all deeper levels.
+fn level_map(levels: &[Maze], level: usize) -> &Maze {
+    &levels[level.min(levels.len() - 1)]
+}
+
+/// Where the portal at the tile leads to under the rules.
+fn jump(levels: &[Maze], rules: &Rules, step: Step) -> Option<Step> {
+    let portal = level_map(levels, step.level).portal(step.pos)?;
+    if rules
+        .one_way
+        .get(&portal.label)
+        .is_some_and(|&side| side != portal.side)
+    {
+        return None;
+    }
+    let level = match (rules.recursive, portal.side) {
+        (false, _) => step.level,
+        (true, Side::Inner) if rules.max_depth.is_none_or(|depth| step.level < depth) => {
+            step.level + 1
+        }
+        (true, Side::Outer) if step.level > 0 => step.level - 1,
+        _ => return None,
+    };
+    let pos = level_map(levels, level).tile(&portal.label, portal.side.opposite())?;
+    Some(Step { level, pos })
 }
 
-fn bfs(orig: Coord, vault: &Vault, portals: &HashMap<Coord, Portal>) -> Vec<(usize, Coord, bool)> {
+/// Shortest path from AA to ZZ on the outermost level with a breadth-first search over the tiles
+/// of all levels. Each level may have a different map with the same portal labels.
+///
+/// Returns `None` if there is no path. Without a deepest level, a recursive maze may have
+/// infinitely many levels to search. A path is then assumed to need no more levels than the
+/// largest number of portals on a map times the number of maps, and the search gives up with an
+/// error after visiting `MAX_TILES_FACTOR` times the tiles of these levels.
+pub fn shortest_path(levels: &[Maze], rules: &Rules) -> Result<Option<Route>> {
+    let outermost = levels.first().ok_or("no levels")?;
+    let endpoint = |label| {
+        outermost
+            .endpoint(label)
+            .ok_or_else(|| format!("no {} on the outermost level", label))
+    };
+    let start = Step {
+        level: 0,
+        pos: endpoint(START)?,
+    };
+    let end = Step {
+        level: 0,
+        pos: endpoint(END)?,
+    };
+
+    let depth = match (rules.recursive, rules.max_depth) {
+        (false, _) => 0,
+        (true, Some(depth)) => depth,
+        (true, None) => {
+            let portals = levels.iter().map(|m| m.portals.len()).max().unwrap_or(0);
+            portals.saturating_mul(levels.len())
+        }
+    };
+    let tiles: Vec<usize> = levels.iter().map(Maze::open_tiles).collect();
+    let repeated = depth.saturating_add(1).saturating_sub(levels.len());
+    let max_tiles = tiles
+        .iter()
+        .take(depth.saturating_add(1))
+        .sum::<usize>()
+        .saturating_add(repeated.saturating_mul(tiles[tiles.len() - 1]))
+        .saturating_mul(MAX_TILES_FACTOR);
+
     let mut q = VecDeque::new();
-    q.push_back((0, orig));
+    q.push_back(start);
+    let mut prev = HashMap::new();
     let mut seen = HashSet::new();
-    seen.insert(orig);
-
-    let mut paths = Vec::new();
-
-    while let Some((dist, pos)) = q.pop_front() {
-        if let Some(portal) = portals.get(&pos) {
-            if dist != 0 {
-                let jump_pos = portals
-                    .iter()
-                    .find(|&(&p_pos, p)| p_pos != pos && p == portal)
-                    .map(|(&pos, _)| pos);
-                let dist = dist + jump_pos.map(|_| 1).unwrap_or(0);
-                let pos = jump_pos.unwrap_or(pos);
-                paths.push((dist, pos, vault.is_outer(pos)));
+    seen.insert(start);
+
+    while let Some(step) = q.pop_front() {
+        if step == end {
+            let mut steps = vec![step];
+            while let Some(&step) = prev.get(steps.last().unwrap()) {
+                steps.push(step);
             }
+            steps.reverse();
+            return Ok(Some(Route { steps }));
+        }
+        if seen.len() > max_tiles {
+            return Err(format!("gave up after visiting {} tiles", max_tiles).into());
         }
 
-        let (x, y) = pos;
-        let neighbors = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)];
-        for &pos in neighbors.iter() {
-            if seen.insert(pos) {
-                match vault.get(pos) {
-                    Some('.') => q.push_back((dist + 1, pos)),
-                    Some(a) if a.is_ascii_uppercase() && portals.get(&pos).is_some() => {
-                        q.push_back((dist + 1, pos))
-                    }
-                    _ => {}
-                }
+        let maze = level_map(levels, step.level);
+        let (x, y) = step.pos;
+        let walks = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ]
+        .iter()
+        .filter(|&&pos| maze.is_open(pos))
+        .map(|&pos| Step {
+            level: step.level,
+            pos,
+        })
+        .collect::<Vec<_>>();
+        let portal = jump(levels, rules, step);
+        for next in walks.into_iter().chain(portal) {
+            if seen.insert(next) {
+                prev.insert(next, step);
+                q.push_back(next);
             }
         }
     }
 
-    paths
-}
-
-fn shortest_path_len(vault: &Vault) -> Option<usize> {
-    let portals = portals(vault);
-    let (&orig, _) = portals.iter().find(|&(_, &p)| p == ('A', 'A'))?;
-    let (&dest, _) = portals.iter().find(|&(_, &p)| p == ('Z', 'Z'))?;
-
-    let paths: HashMap<Coord, _> = portals
-        .keys()
-        .map(|&orig| (orig, bfs(orig, vault, &portals)))
-        .collect();
-
-    dijkstra(orig, dest, &paths)
+    Ok(None)
 }
 
-fn shortest_path_len_with_levels(vault: &Vault) -> Option<usize> {
-    let portals = portals(vault);
-    let (&orig, _) = portals.iter().find(|&(_, &p)| p == ('A', 'A'))?;
-    let (&dest, _) = portals.iter().find(|&(_, &p)| p == ('Z', 'Z'))?;
-
-    let paths: HashMap<Coord, _> = portals
-        .keys()
-        .map(|&orig| (orig, bfs(orig, vault, &portals)))
-        .collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    multilevel_dijksta(orig, dest, &paths)
-}
+    const EXAMPLE: &str = "         A
+         A
+  #######.#########
+  #######.........#
+  #######.#######.#
+  #######.#######.#
+  #######.#######.#
+  #####  B    ###.#
+BC...##  C    ###.#
+  ##.##       ###.#
+  ##...DE  F  ###.#
+  #####    G  ###.#
+  #########.#####.#
+DE..#######...###.#
+  #.#########.###.#
+FG..#########.....#
+  ###########.#####
+             Z
+             Z";
 
-fn dijkstra(
-    orig: Coord,
-    dest: Coord,
-    paths: &HashMap<Coord, Vec<(usize, Coord, bool)>>,
-) -> Option<usize> {
-    let mut heap = BinaryHeap::new();
-    heap.push(Reverse((0, orig)));
+    #[test]
+    fn test_plain() {
+        let levels = [Maze::parse(EXAMPLE).unwrap()];
+        let route = shortest_path(&levels, &Rules::default()).unwrap().unwrap();
+        assert_eq!(route.len(), 23);
+        assert_eq!(route.steps[0].pos, (9, 2));
+        assert_eq!(route.steps.last().unwrap().pos, (13, 16));
+        let labels: Vec<_> = route
+            .transitions(&levels)
+            .into_iter()
+            .map(|(label, _, _)| label)
+            .collect();
+        assert_eq!(labels, vec!["BC", "DE", "FG"]);
 
-    let mut distances = HashMap::new();
-    distances.insert(orig, 0);
+        // without the first portal, walking directly is shorter
+        let mut rules = Rules::default();
+        rules.one_way.insert("BC".into(), Side::Outer);
+        let route = shortest_path(&levels, &rules).unwrap().unwrap();
+        assert_eq!(route.len(), 26);
+        assert!(route.transitions(&levels).is_empty());
+    }
 
-    while let Some(Reverse((dist, pos))) = heap.pop() {
-        if pos == dest {
-            return Some(dist);
-        }
+    #[test]
+    fn test_recursive() {
+        let levels = [Maze::parse(EXAMPLE).unwrap()];
+        let maze = &levels[0];
+        let rules = Rules {
+            recursive: true,
+            ..Rules::default()
+        };
+        let route = shortest_path(&levels, &rules).unwrap().unwrap();
+        assert_eq!(route.len(), 26);
+        assert_eq!(route.max_level(), 0);
 
-        for &(portal_dist, pos, _is_outer) in &paths[&pos] {
-            let dist = dist + portal_dist;
-            match distances.entry(pos) {
-                Entry::Vacant(entry) => {
-                    entry.insert(dist);
-                    heap.push(Reverse((dist, pos)));
-                }
-                Entry::Occupied(mut entry) if dist < *entry.get() => {
-                    entry.insert(dist);
-                    heap.push(Reverse((dist, pos)));
-                }
-                _ => {}
-            }
-        }
+        assert_eq!(maze.tile("BC", Side::Inner), Some((9, 6)));
+        assert_eq!(maze.tile("BC", Side::Outer), Some((2, 8)));
+        assert_eq!(
+            maze.portal((2, 8)),
+            Some(&Portal {
+                label: "BC".into(),
+                side: Side::Outer
+            })
+        );
+        assert!(Maze::parse(" AB#\n  #.#").is_err());
     }
 
-    None
-}
+    #[test]
+    fn test_levels() {
+        // AA and ZZ are only connected through a deeper level with a different map
+        let outermost = Maze::parse(
+            "    A
+    A
+  ##.########
+  ##...######
+  ####.######
+  ### X   ###
+  ### Y   ###
+  ###     ###
+  ###   U ###
+  ###   V ###
+  ######.####
+  ######...##
+  ########.##
+          Z
+          Z",
+        )
+        .unwrap();
+        let inner = Maze::parse(
+            "
 
-fn multilevel_dijksta(
-    orig: Coord,
-    dest: Coord,
-    paths: &HashMap<Coord, Vec<(usize, Coord, bool)>>,
-) -> Option<usize> {
-    let mut heap = BinaryHeap::new();
-    heap.push(Reverse((0, orig, 0_isize))); // (dist, pos, level)
-
-    let mut distances = HashMap::new();
-    distances.insert((orig, 0_isize), 0); // (pos, level) -> dist
-
-    while let Some(Reverse((dist, pos, level))) = heap.pop() {
-        for &(portal_dist, pos, is_outer) in &paths[&pos] {
-            if pos == orig {
-                continue;
-            } else if pos == dest {
-                if level == 0 {
-                    return Some(dist + portal_dist);
-                } else {
-                    continue;
-                }
+  ###########
+  #.........#
+  #.#######.#
+  #.#     #.#
+XY..#     #.#
+  ###     #.#
+  ###     #..UV
+  ###     ###
+  ###########
+  ###########
+  ###########",
+        )
+        .unwrap();
+        let levels = [outermost, inner];
+        let rules = Rules {
+            recursive: true,
+            ..Rules::default()
+        };
+        let route = shortest_path(&levels, &rules).unwrap().unwrap();
+        assert_eq!(route.len(), 28);
+        assert_eq!(route.max_level(), 1);
+        assert_eq!(route.transitions(&levels), vec![("XY", 0, 1), ("UV", 1, 0)]);
+        assert_eq!(
+            route.steps[5],
+            Step {
+                level: 1,
+                pos: (2, 6)
             }
+        );
 
-            let dist = dist + portal_dist;
-            let level = level + if is_outer { 1 } else { -1 };
-            if level < 0 {
-                continue;
-            }
+        let shallow = Rules {
+            max_depth: Some(0),
+            ..rules.clone()
+        };
+        assert_eq!(shortest_path(&levels, &shallow).unwrap(), None);
+        // the outermost map repeats on all levels, where the outer XY is missing
+        assert_eq!(shortest_path(&levels[..1], &rules).unwrap(), None);
+        assert_eq!(
+            shortest_path(&levels[1..], &rules).unwrap_err().to_string(),
+            "no AA on the outermost level"
+        );
 
-            match distances.entry((pos, level)) {
-                Entry::Vacant(entry) => {
-                    entry.insert(dist);
-                    heap.push(Reverse((dist, pos, level)));
-                }
-                Entry::Occupied(mut entry) if dist < *entry.get() => {
-                    entry.insert(dist);
-                    heap.push(Reverse((dist, pos, level)));
-                }
-                _ => {}
-            }
-        }
+        // each middle level only leads one level deeper on the way down and one level up on
+        // the way back, so the path needs more levels than any map has portals
+        let middle = Maze::parse(
+            "
+
+  ###########
+  #....######
+  #.##.######
+  #.# X   ###
+XY..# Y   ###
+  ###     ###
+  ###   U #..UV
+  ###   V #.#
+  ######....#
+  ###########",
+        )
+        .unwrap();
+        let [outermost, inner] = levels;
+        let levels = vec![
+            outermost,
+            middle.clone(),
+            middle.clone(),
+            middle.clone(),
+            middle,
+            inner,
+        ];
+        let route = shortest_path(&levels, &rules).unwrap().unwrap();
+        assert_eq!(route.max_level(), 5);
+        assert!(levels.iter().all(|m| m.portals.len() < 5));
     }
 
-    None
+    #[test]
+    fn test_unsolvable() {
+        // ZZ is walled off, while BC, DE and FG lead ever deeper
+        let walled = EXAMPLE
+            .replace("  #.#########.###.#", "  #.#########..##.#")
+            .replace("FG..#########.....#", "FG..##########....#");
+        let levels = [Maze::parse(&walled).unwrap()];
+        assert_eq!(shortest_path(&levels, &Rules::default()).unwrap(), None);
+        let rules = Rules {
+            recursive: true,
+            ..Rules::default()
+        };
+        assert_eq!(
+            shortest_path(&levels, &rules).unwrap_err().to_string(),
+            "gave up after visiting 846 tiles"
+        );
+    }
 }
@@ -1,58 +1,51 @@
+pub mod springscript;
+pub mod synth;
+
 use crate::day09::{execute, parse, Memory};
+use springscript::{Hull, Mode, Script};
 
 type Error = Box<dyn std::error::Error>;
 type Result<T> = std::result::Result<T, Error>;
 
+/// Number of attempts with the real droid before giving up.
+const MAX_ATTEMPTS: usize = 50;
+
 pub fn solve(input: &str) -> Result<(i64, i64)> {
     let mem = parse(input);
 
-    // +--->
-    // @ABCD
-    // #####
-    //
-    // (~A OR ~B OR ~C) AND D
-    let walk_script = b"\
-        NOT A J
-        NOT B T
-        OR T J
-        NOT C T
-        OR T J
-        AND D J
-        WALK
-    ";
-    let part1 = run(mem.clone(), walk_script)?;
+    let (_, part1) = learn(&mem, Mode::Walk)?;
+    let (_, part2) = learn(&mem, Mode::Run)?;
 
-    // +--->
-    // @ABCDEFGHI
-    // ##########
-    //
-    // ((~A OR ~B OR ~C) AND D) AND
-    // (
-    //  ((~E OR ~F OR ~G) AND H) OR
-    //  (E AND F AND G AND H) OR
-    //  (E AND (~F OR ~G OR ~H OR ~K))
-    // )
-    // <=> (~A OR ~B OR ~C) AND D AND (E OR H)
-    // <=> ~(A AND B AND C) AND D AND (E OR H)
-
-    let run_script = b"\
-        NOT T T
-        AND A T
-        AND B T
-        AND C T
-        NOT T T
-        AND D T
-        OR E J
-        OR H J
-        AND T J
-        RUN
-    ";
-    let part2 = run(mem, run_script)?;
     Ok((part1, part2))
 }
 
-fn run(mut mem: Memory, script: &[u8]) -> Result<i64> {
+/// Finds a script which gets the droid across the whole hull, with the reported hull damage.
+///
+/// Starting without any known hull, a shortest script for the hulls seen so far is synthesized
+/// and sent to the droid. Each fall reveals another hull, until the droid makes it across.
+pub fn learn(mem: &Memory, mode: Mode) -> Result<(Script, i64)> {
+    let mut hulls: Vec<Hull> = Vec::new();
+    for _ in 0..MAX_ATTEMPTS {
+        let script = synth::synthesize(&hulls, mode)?;
+        match run(mem.clone(), &script)? {
+            Ok(damage) => return Ok((script, damage)),
+            Err(report) => {
+                let hull = synth::parse_fall(&report)?;
+                if hulls.contains(&hull) {
+                    return Err(format!("droid fell on the known hull {}", hull).into());
+                }
+                hulls.push(hull);
+            }
+        }
+    }
+    Err(format!("droid did not make it across in {} attempts", MAX_ATTEMPTS).into())
+}
+
+/// Returns the hull damage, or the droid's report if it did not make it across.
+fn run(mut mem: Memory, script: &Script) -> Result<std::result::Result<i64, String>> {
+    let script = script.to_string().into_bytes();
     let mut index = 0;
+    let mut report = String::new();
     let mut retval = None;
     let mut ip = Some(0);
     while let Some(next_ip) = ip {
@@ -60,13 +53,13 @@ fn run(mut mem: Memory, script: &[u8]) -> Result<i64> {
             &mut mem,
             next_ip,
             || {
-                let value = script[index] as i64;
+                let value = script.get(index).copied().unwrap_or(b'\n') as i64;
                 index += 1;
                 value
             },
             |value| {
                 if value < 128 {
-                    print!("{}", value as u8 as char);
+                    report.push(value as u8 as char);
                 } else {
                     retval = Some(value);
                 }
@@ -77,5 +70,5 @@ fn run(mut mem: Memory, script: &[u8]) -> Result<i64> {
         }
     }
 
-    retval.ok_or_else(|| "did not make it across".into())
+    Ok(retval.ok_or(report))
 }
@@ -0,0 +1,355 @@
+//! Springscript: parsing, validation and a local simulation of the springdroid.
+//!
+//! The droid reads the ground at the distances 1 to 4 (registers `A` to `D`) when walking, or 1
+//! to 9 (`A` to `I`) when running, evaluates the script with `T` and `J` cleared, and jumps if `J`
+//! is set. A jump lands four tiles ahead.
+
+use std::fmt;
+
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
+
+/// Most instructions the springdroid's memory holds.
+pub const MAX_INSTRUCTIONS: usize = 15;
+/// Distance covered by a jump.
+pub const JUMP: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Register {
+    /// Ground sensor at the distance `1..=9`.
+    Sensor(u8),
+    T,
+    J,
+}
+
+impl Register {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.as_bytes() {
+            [b'T'] => Ok(Register::T),
+            [b'J'] => Ok(Register::J),
+            [c @ b'A'..=b'I'] => Ok(Register::Sensor(c - b'A' + 1)),
+            _ => Err(format!("invalid register: {:?}", s).into()),
+        }
+    }
+
+    pub fn is_writable(self) -> bool {
+        matches!(self, Register::T | Register::J)
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Register::Sensor(distance) => write!(f, "{}", (b'A' + distance - 1) as char),
+            Register::T => write!(f, "T"),
+            Register::J => write!(f, "J"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    And,
+    Or,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Instruction {
+    pub op: Op,
+    pub src: Register,
+    pub dst: Register,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self.op {
+            Op::And => "AND",
+            Op::Or => "OR",
+            Op::Not => "NOT",
+        };
+        write!(f, "{} {} {}", op, self.src, self.dst)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Walk,
+    Run,
+}
+
+impl Mode {
+    /// Number of ground sensors available.
+    pub fn range(self) -> u8 {
+        match self {
+            Mode::Walk => 4,
+            Mode::Run => 9,
+        }
+    }
+}
+
+/// Validated script ending with `WALK` or `RUN`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Script {
+    instructions: Vec<Instruction>,
+    mode: Mode,
+}
+
+impl Script {
+    pub fn new(instructions: Vec<Instruction>, mode: Mode) -> Result<Self> {
+        if instructions.len() > MAX_INSTRUCTIONS {
+            return Err(format!(
+                "{} instructions exceed the limit of {}",
+                instructions.len(),
+                MAX_INSTRUCTIONS
+            )
+            .into());
+        }
+        for (i, instr) in instructions.iter().enumerate() {
+            if !instr.dst.is_writable() {
+                return Err(format!("instruction {}: cannot write to {}", i + 1, instr.dst).into());
+            }
+            if let Register::Sensor(distance) = instr.src {
+                if distance > mode.range() {
+                    return Err(format!(
+                        "instruction {}: sensor {} is not available in {:?} mode",
+                        i + 1,
+                        instr.src,
+                        mode
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(Self { instructions, mode })
+    }
+
+    /// Parses one instruction per line; blank lines and surrounding whitespace are ignored.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut instructions = Vec::new();
+        let mut mode = None;
+        for (i, line) in text.lines().map(str::trim).enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            let err = |msg: String| -> Error { format!("line {}: {}", i + 1, msg).into() };
+            if mode.is_some() {
+                return Err(err(format!("unexpected {:?} after the end", line)));
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[..] {
+                ["WALK"] => mode = Some(Mode::Walk),
+                ["RUN"] => mode = Some(Mode::Run),
+                [op, src, dst] => {
+                    let op = match op {
+                        "AND" => Op::And,
+                        "OR" => Op::Or,
+                        "NOT" => Op::Not,
+                        _ => return Err(err(format!("unknown instruction {:?}", op))),
+                    };
+                    let src = Register::parse(src).map_err(|e| err(e.to_string()))?;
+                    let dst = Register::parse(dst).map_err(|e| err(e.to_string()))?;
+                    instructions.push(Instruction { op, src, dst });
+                }
+                _ => return Err(err(format!("invalid instruction {:?}", line))),
+            }
+        }
+        let mode = mode.ok_or("script does not end with WALK or RUN")?;
+        Self::new(instructions, mode)
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Whether the droid jumps, where bit `i` of `ground` is set if there is ground at the
+    /// distance `i + 1`.
+    pub fn jumps(&self, ground: u16) -> bool {
+        let (mut t, mut j) = (false, false);
+        for instr in &self.instructions {
+            let src = match instr.src {
+                Register::Sensor(distance) => ground & 1 << (distance - 1) != 0,
+                Register::T => t,
+                Register::J => j,
+            };
+            let dst = if instr.dst == Register::T {
+                &mut t
+            } else {
+                &mut j
+            };
+            *dst = match instr.op {
+                Op::And => *dst && src,
+                Op::Or => *dst || src,
+                Op::Not => !src,
+            };
+        }
+        j
+    }
+}
+
+/// Script as sent to the droid, with a newline after each line.
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for instr in &self.instructions {
+            writeln!(f, "{}", instr)?;
+        }
+        match self.mode {
+            Mode::Walk => writeln!(f, "WALK"),
+            Mode::Run => writeln!(f, "RUN"),
+        }
+    }
+}
+
+/// Ground of the hull, starting at the droid; the hull continues with ground after its end.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hull {
+    ground: Vec<bool>,
+}
+
+impl Hull {
+    /// Parses `#` for ground and `.` for holes, e.g. `#####.#..####`.
+    pub fn parse(s: &str) -> Result<Self> {
+        let ground = s
+            .trim()
+            .chars()
+            .map(|c| match c {
+                '#' => Ok(true),
+                '.' => Ok(false),
+                _ => Err(format!("invalid hull tile {:?}", c)),
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        if ground.first() != Some(&true) {
+            return Err("the droid must start on ground".into());
+        }
+        Ok(Self { ground })
+    }
+
+    pub fn len(&self) -> usize {
+        self.ground.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ground.is_empty()
+    }
+
+    pub fn is_ground(&self, pos: usize) -> bool {
+        self.ground.get(pos).copied().unwrap_or(true)
+    }
+
+    /// Sensor readings at the position for the given number of sensors.
+    pub fn sensors(&self, pos: usize, range: u8) -> u16 {
+        (0..range as usize)
+            .filter(|&i| self.is_ground(pos + 1 + i))
+            .fold(0, |ground, i| ground | 1 << i)
+    }
+}
+
+impl fmt::Display for Hull {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for &ground in &self.ground {
+            write!(f, "{}", if ground { '#' } else { '.' })?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The droid got past the end of the hull.
+    Survived,
+    /// The droid fell into the hole at `at` after jumping or stepping from `from`.
+    Fell { from: usize, at: usize },
+}
+
+/// Moves the droid along the hull as the script tells it.
+pub fn simulate(script: &Script, hull: &Hull) -> Outcome {
+    let mut pos = 0;
+    while pos < hull.len() {
+        let from = pos;
+        pos += if script.jumps(hull.sensors(pos, script.mode.range())) {
+            JUMP
+        } else {
+            1
+        };
+        if !hull.is_ground(pos) {
+            return Outcome::Fell { from, at: pos };
+        }
+    }
+    Outcome::Survived
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JUMP_EARLY: &str = "
+        NOT A J
+        NOT B T
+        OR T J
+        NOT C T
+        OR T J
+        AND D J
+        WALK
+    ";
+
+    #[test]
+    fn test_parse() {
+        let script = Script::parse(JUMP_EARLY).unwrap();
+        assert_eq!(script.instructions().len(), 6);
+        assert_eq!(script.mode(), Mode::Walk);
+        assert_eq!(
+            script.instructions()[1],
+            Instruction {
+                op: Op::Not,
+                src: Register::Sensor(2),
+                dst: Register::T
+            }
+        );
+        assert!(script.to_string().starts_with("NOT A J\nNOT B T\n"));
+        assert_eq!(Script::parse(&script.to_string()).unwrap(), script);
+
+        assert!(Script::parse("NOT A J").is_err());
+        assert!(Script::parse("NOT A B\nWALK").is_err());
+        assert!(Script::parse("NOT E J\nWALK").is_err());
+        assert!(Script::parse("NOT E J\nRUN").is_ok());
+        assert!(Script::parse("XOR A J\nWALK").is_err());
+        assert!(Script::parse("NOT K J\nRUN").is_err());
+        assert!(Script::parse("WALK\nNOT A J").is_err());
+        let long = "OR A J\n".repeat(MAX_INSTRUCTIONS + 1) + "WALK";
+        assert!(Script::parse(&long).is_err());
+        let err = Script::parse("NOT A J\nNOT X T\nWALK").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: invalid register: \"X\"");
+    }
+
+    #[test]
+    fn test_simulate() {
+        let script = Script::parse(JUMP_EARLY).unwrap();
+        assert_eq!(
+            simulate(&script, &Hull::parse("#####.#..####").unwrap()),
+            Outcome::Survived
+        );
+
+        // jumps as soon as the first hole is seen and then cannot clear the next one
+        let hull = Hull::parse("#####.#.##.#.####").unwrap();
+        assert_eq!(simulate(&script, &hull), Outcome::Fell { from: 6, at: 7 });
+
+        // the well-known fix looks further ahead
+        let script = Script::parse(
+            "NOT A T\nNOT B J\nOR J T\nNOT C J\nOR J T\nAND D T\nNOT E J\nNOT J J\nOR H J\nAND T J\nRUN",
+        )
+        .unwrap();
+        assert_eq!(simulate(&script, &hull), Outcome::Survived);
+
+        let never = Script::parse("WALK").unwrap();
+        assert_eq!(
+            simulate(&never, &Hull::parse("##.#").unwrap()),
+            Outcome::Fell { from: 1, at: 2 }
+        );
+        assert!(Hull::parse(".##").is_err());
+        assert!(Hull::parse("#@#").is_err());
+    }
+}
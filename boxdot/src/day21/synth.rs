@@ -0,0 +1,296 @@
+//! Synthesis of springscripts which get the droid across known hulls.
+//!
+//! First, all ways across the hulls are collected as the decisions to jump or not at the sensor
+//! readings the droid meets on them, and the registers are tracked as truth tables over these
+//! readings. All instructions are tried on the tables of `T` and `J` in a breadth-first search,
+//! until the droid following `J` crosses all hulls.
+
+use super::springscript::{simulate, Hull, Instruction, Mode, Op, Outcome, Register, Script};
+use super::springscript::{JUMP, MAX_INSTRUCTIONS};
+use std::collections::{HashMap, HashSet};
+
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
+
+/// Number of register states after which the search gives up.
+const MAX_STATES: usize = 5_000_000;
+/// Number of ways across the hulls after which no more are collected.
+const MAX_WAYS: usize = 10_000;
+
+/// Decision to jump at each sensor reading met on the way across the hulls.
+type Way = HashMap<u16, bool>;
+
+/// Collects the ways across the hulls from the given hull and position on.
+fn ways(hulls: &[Hull], range: u8, hull: usize, pos: usize, way: &mut Way, out: &mut Vec<Way>) {
+    if out.len() >= MAX_WAYS {
+        return;
+    }
+    let (hull, pos) = match hulls.get(hull) {
+        None => {
+            out.push(way.clone());
+            return;
+        }
+        Some(h) if pos >= h.len() => return ways(hulls, range, hull + 1, 0, way, out),
+        Some(_) => (hull, pos),
+    };
+    let reading = hulls[hull].sensors(pos, range);
+    let decisions = match way.get(&reading) {
+        Some(&jump) => vec![jump],
+        None => vec![false, true],
+    };
+    for jump in decisions {
+        let next = pos + if jump { JUMP } else { 1 };
+        if !hulls[hull].is_ground(next) {
+            continue;
+        }
+        let is_new = way.insert(reading, jump).is_none();
+        ways(hulls, range, hull, next, way, out);
+        if is_new {
+            way.remove(&reading);
+        }
+    }
+}
+
+/// Truth table over the readings of interest.
+type Table = u128;
+
+/// Tables of `T` and `J`, and the previous node with the instruction from it.
+type Node = ((Table, Table), Option<(usize, Instruction)>);
+
+/// Shortest script which gets the droid across all hulls.
+pub fn synthesize(hulls: &[Hull], mode: Mode) -> Result<Script> {
+    let mut all_ways = Vec::new();
+    ways(hulls, mode.range(), 0, 0, &mut Way::new(), &mut all_ways);
+    if all_ways.is_empty() {
+        return Err("no way across the hulls".into());
+    }
+
+    // a droid following a script only meets the readings of a way it agrees with
+    let mut readings: Vec<u16> = all_ways.iter().flat_map(|w| w.keys().copied()).collect();
+    readings.sort_unstable();
+    readings.dedup();
+    if readings.len() > Table::BITS as usize {
+        return Err(format!("too many distinct readings: {}", readings.len()).into());
+    }
+    let bit = |reading: u16| readings.binary_search(&reading).ok();
+    let courses: Vec<Vec<Option<usize>>> = hulls
+        .iter()
+        .map(|hull| {
+            (0..hull.len())
+                .map(|pos| bit(hull.sensors(pos, mode.range())))
+                .collect()
+        })
+        .collect();
+    // the latest hulls, on which the shorter scripts fell, rule out most tables first
+    let crosses = |j: Table| {
+        hulls.iter().zip(&courses).rev().all(|(hull, course)| {
+            let mut pos = 0;
+            while pos < hull.len() {
+                pos += match course[pos] {
+                    Some(i) if j & 1 << i != 0 => JUMP,
+                    Some(_) => 1,
+                    None => return false,
+                };
+                if !hull.is_ground(pos) {
+                    return false;
+                }
+            }
+            true
+        })
+    };
+
+    let all: Table = (0..readings.len()).fold(0, |all, i| all | 1 << i);
+    let sensors: Vec<Table> = (0..mode.range())
+        .map(|sensor| {
+            (0..readings.len())
+                .filter(|&i| readings[i] & 1 << sensor != 0)
+                .fold(0, |table, i| table | 1 << i)
+        })
+        .collect();
+    let sources: Vec<Register> = (1..=mode.range())
+        .map(Register::Sensor)
+        .chain(vec![Register::T, Register::J])
+        .collect();
+    let mut instructions = Vec::new();
+    for &op in &[Op::And, Op::Or, Op::Not] {
+        for &src in &sources {
+            for &dst in &[Register::T, Register::J] {
+                instructions.push(Instruction { op, src, dst });
+            }
+        }
+    }
+    let execute = |(t, j): (Table, Table), instr: Instruction| {
+        let src = match instr.src {
+            Register::Sensor(distance) => sensors[distance as usize - 1],
+            Register::T => t,
+            Register::J => j,
+        };
+        let dst = if instr.dst == Register::T { t } else { j };
+        let table = match instr.op {
+            Op::And => dst & src,
+            Op::Or => dst | src,
+            Op::Not => !src & all,
+        };
+        if instr.dst == Register::T {
+            (table, j)
+        } else {
+            (t, table)
+        }
+    };
+
+    // a shortest script ends by writing `J`, and every stored node has its `J` checked already
+    let mut nodes: Vec<Node> = vec![((0, 0), None)];
+    if crosses(0) {
+        return Script::new(Vec::new(), mode);
+    }
+    let mut seen = HashSet::new();
+    seen.insert(nodes[0].0);
+    let mut level = 0..1;
+    for _ in 0..MAX_INSTRUCTIONS {
+        // the scripts one instruction longer are checked before any node is stored for them
+        for i in level.clone() {
+            let state = nodes[i].0;
+            for &instr in &instructions {
+                if instr.dst == Register::J {
+                    let (_, j) = execute(state, instr);
+                    if j != state.1 && crosses(j) {
+                        let mut script = script(&nodes, i);
+                        script.push(instr);
+                        return Script::new(script, mode);
+                    }
+                }
+            }
+        }
+        let start = nodes.len();
+        for i in level {
+            let state = nodes[i].0;
+            for &instr in &instructions {
+                let next = execute(state, instr);
+                if seen.insert(next) {
+                    nodes.push((next, Some((i, instr))));
+                }
+            }
+            if nodes.len() > MAX_STATES {
+                return Err(format!("gave up after {} register states", MAX_STATES).into());
+            }
+        }
+        if start == nodes.len() {
+            break;
+        }
+        level = start..nodes.len();
+    }
+    Err(format!("no script with up to {} instructions", MAX_INSTRUCTIONS).into())
+}
+
+fn script(nodes: &[Node], mut i: usize) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    while let Some((prev, instr)) = nodes[i].1 {
+        instructions.push(instr);
+        i = prev;
+    }
+    instructions.reverse();
+    instructions
+}
+
+/// Extracts the hull from the droid's report of its fall.
+pub fn parse_fall(report: &str) -> Result<Hull> {
+    let (_, fall) = report
+        .split_once("Didn't make it across")
+        .ok_or("report does not describe a fall")?;
+    let row = fall
+        .lines()
+        .map(str::trim)
+        .find(|l| l.contains('#') && l.chars().all(|c| c == '#' || c == '.'))
+        .ok_or("report does not show the hull")?;
+    Hull::parse(row)
+}
+
+/// Checks the script against the hulls locally and returns the first one the droid falls on.
+pub fn counterexample<'a>(script: &Script, hulls: &'a [Hull]) -> Option<&'a Hull> {
+    hulls
+        .iter()
+        .find(|hull| simulate(script, hull) != Outcome::Survived)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hulls(rows: &[&str]) -> Vec<Hull> {
+        rows.iter().map(|row| Hull::parse(row).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_synthesize() {
+        // without any hull, the droid does not need to do anything
+        let script = synthesize(&[], Mode::Walk).unwrap();
+        assert!(script.instructions().is_empty());
+
+        let single = hulls(&["#####.###########"]);
+        let script = synthesize(&single, Mode::Walk).unwrap();
+        assert_eq!(script.instructions().len(), 1);
+        assert_eq!(counterexample(&script, &single), None);
+
+        let examples = hulls(&[
+            "#####.###########",
+            "#####..#.########",
+            "#####...#########",
+            "#####.#..########",
+        ]);
+        let script = synthesize(&examples, Mode::Walk).unwrap();
+        assert_eq!(counterexample(&script, &examples), None);
+        // every shorter script falls somewhere
+        let len = script.instructions().len();
+        assert_eq!(len, 4);
+        for shorter in scripts(len - 1, Mode::Walk) {
+            assert!(counterexample(&shorter, &examples).is_some(), "{}", shorter);
+        }
+    }
+
+    /// All scripts with up to the given number of instructions.
+    fn scripts(max_len: usize, mode: Mode) -> Vec<Script> {
+        let registers: Vec<Register> = (1..=mode.range())
+            .map(Register::Sensor)
+            .chain(vec![Register::T, Register::J])
+            .collect();
+        let mut instructions = Vec::new();
+        for &op in &[Op::And, Op::Or, Op::Not] {
+            for &src in &registers {
+                for &dst in &[Register::T, Register::J] {
+                    instructions.push(Instruction { op, src, dst });
+                }
+            }
+        }
+        let mut scripts = vec![Vec::new()];
+        let mut level = vec![Vec::new()];
+        for _ in 0..max_len {
+            level = level
+                .iter()
+                .flat_map(|prefix: &Vec<Instruction>| {
+                    instructions.iter().map(move |&instr| {
+                        let mut script = prefix.clone();
+                        script.push(instr);
+                        script
+                    })
+                })
+                .collect();
+            scripts.extend(level.iter().cloned());
+        }
+        scripts
+            .into_iter()
+            .map(|instructions| Script::new(instructions, mode).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_fall() {
+        let report = "Input instructions:\n\nWalking...\n\n\nDidn't make it across:\n\n\
+            .................\n.................\n@................\n#####.#.#########\n\n\
+            .................\n.@...............\n";
+        assert_eq!(
+            parse_fall(report).unwrap(),
+            Hull::parse("#####.#.#########").unwrap()
+        );
+        assert!(parse_fall("Walking...\n").is_err());
+    }
+}
@@ -20,6 +20,22 @@ pub fn solve(input: &str) -> (u32, usize) {
     (part1, part2)
 }
 
+/// Returns all generations of bugs up to and including the first one which appeared before.
+///
+/// Each generation is the 5x5 grid stored row by row, `true` being a bug.
+pub fn generations(input: &str) -> Vec<Vec<bool>> {
+    let mut state = parse(input);
+    let mut prev = HashSet::new();
+    let mut generations = Vec::new();
+    loop {
+        generations.push((0..5 * 5).map(|i| is_bug_at_index(state, i)).collect());
+        if !prev.insert(state) {
+            return generations;
+        }
+        state = next_gen(state);
+    }
+}
+
 fn parse(input: &str) -> u32 {
     input
         .bytes()
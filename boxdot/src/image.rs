@@ -0,0 +1,294 @@
+//! Export of 2D grids as PPM, PNG or SVG images.
+//!
+//! Grids are first rasterized into an [`Image`] of RGB pixels using a palette, then written with
+//! every grid cell scaled up to `scale` x `scale` pixels. Sequences of images are written as
+//! animated PNGs.
+
+use crate::tui::{Canvas, Coord};
+use std::io::Write;
+use std::path::Path;
+
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
+
+pub type Rgb = [u8; 3];
+
+pub const BLACK: Rgb = [0, 0, 0];
+pub const WHITE: Rgb = [255, 255, 255];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Ppm,
+    Png,
+    Svg,
+}
+
+impl Format {
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("ppm") => Ok(Format::Ppm),
+            Some("png") => Ok(Format::Png),
+            Some("svg") => Ok(Format::Svg),
+            _ => Err(format!("unknown image format: {}", path.display()).into()),
+        }
+    }
+}
+
+impl Image {
+    /// Rasterizes a dense grid stored row by row.
+    pub fn from_dense<T>(cells: &[T], width: usize, palette: impl Fn(&T) -> Rgb) -> Result<Self> {
+        if width == 0 || !cells.len().is_multiple_of(width) {
+            return Err(
+                format!("{} cells do not fill rows of width {}", cells.len(), width).into(),
+            );
+        }
+        Ok(Self {
+            width,
+            height: cells.len() / width,
+            pixels: cells.iter().map(palette).collect(),
+        })
+    }
+
+    /// Rasterizes the bounding box of a canvas; coordinates without a cell get `background`.
+    pub fn from_canvas<T>(
+        canvas: &Canvas<T>,
+        palette: impl Fn(&T) -> Rgb,
+        background: Rgb,
+    ) -> Self {
+        match canvas.bounds() {
+            Some(bounds) => Self::from_canvas_in(canvas, bounds, palette, background),
+            None => Self {
+                width: 0,
+                height: 0,
+                pixels: Vec::new(),
+            },
+        }
+    }
+
+    /// Rasterizes the inclusive rectangle between the given corners of a canvas.
+    pub fn from_canvas_in<T>(
+        canvas: &Canvas<T>,
+        ((x0, y0), (x1, y1)): (Coord, Coord),
+        palette: impl Fn(&T) -> Rgb,
+        background: Rgb,
+    ) -> Self {
+        let width = (x1 - x0 + 1).max(0) as usize;
+        let height = (y1 - y0 + 1).max(0) as usize;
+        let pixels = (y0..=y1)
+            .flat_map(|y| (x0..=x1).map(move |x| (x, y)))
+            .map(|pos| canvas.get(pos).map(&palette).unwrap_or(background))
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Rgb> {
+        if x < self.width {
+            self.pixels.get(x + y * self.width).copied()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the raw RGB bytes of the image scaled by `scale`.
+    fn scaled_rgb(&self, scale: usize) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.pixels.len() * scale * scale * 3);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            for _ in 0..scale {
+                for pixel in row {
+                    for _ in 0..scale {
+                        data.extend_from_slice(pixel);
+                    }
+                }
+            }
+        }
+        data
+    }
+
+    pub fn write_ppm(&self, mut w: impl Write, scale: usize) -> Result<()> {
+        write!(
+            w,
+            "P6\n{} {}\n255\n",
+            self.width * scale,
+            self.height * scale
+        )?;
+        w.write_all(&self.scaled_rgb(scale))?;
+        Ok(())
+    }
+
+    pub fn write_png(&self, w: impl Write, scale: usize) -> Result<()> {
+        let mut encoder = png_encoder(w, self, scale)?;
+        encoder.set_compression(png::Compression::Best);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.scaled_rgb(scale))?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Writes one square per pixel, merging horizontal runs of the same color.
+    pub fn write_svg(&self, mut w: impl Write, scale: usize) -> Result<()> {
+        writeln!(
+            w,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" shape-rendering="crispEdges">"#,
+            self.width * scale,
+            self.height * scale
+        )?;
+        for (y, row) in self.pixels.chunks(self.width.max(1)).enumerate() {
+            let mut x = 0;
+            while x < row.len() {
+                let color = row[x];
+                let len = row[x..].iter().take_while(|&&c| c == color).count();
+                writeln!(
+                    w,
+                    r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#{:02x}{:02x}{:02x}"/>"##,
+                    x * scale,
+                    y * scale,
+                    len * scale,
+                    scale,
+                    color[0],
+                    color[1],
+                    color[2]
+                )?;
+                x += len;
+            }
+        }
+        writeln!(w, "</svg>")?;
+        Ok(())
+    }
+
+    pub fn write(&self, w: impl Write, format: Format, scale: usize) -> Result<()> {
+        match format {
+            Format::Ppm => self.write_ppm(w, scale),
+            Format::Png => self.write_png(w, scale),
+            Format::Svg => self.write_svg(w, scale),
+        }
+    }
+
+    /// Saves the image in the format given by the extension of `path`.
+    pub fn save(&self, path: impl AsRef<Path>, scale: usize) -> Result<()> {
+        let format = Format::from_path(&path)?;
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write(file, format, scale)
+    }
+}
+
+fn png_encoder<W: Write>(w: W, image: &Image, scale: usize) -> Result<png::Encoder<'static, W>> {
+    if scale == 0 || image.width == 0 || image.height == 0 {
+        return Err("cannot encode an empty image".into());
+    }
+    let mut encoder = png::Encoder::new(
+        w,
+        (image.width * scale) as u32,
+        (image.height * scale) as u32,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    Ok(encoder)
+}
+
+/// Writes the frames as an animated PNG which loops forever.
+///
+/// All frames must have the same dimensions.
+pub fn write_animation(frames: &[Image], w: impl Write, scale: usize, delay_ms: u16) -> Result<()> {
+    let first = frames.first().ok_or("no frames to animate")?;
+    if let Some(frame) = frames
+        .iter()
+        .find(|frame| (frame.width, frame.height) != (first.width, first.height))
+    {
+        return Err(format!(
+            "frame of size {}x{} differs from first frame of size {}x{}",
+            frame.width, frame.height, first.width, first.height
+        )
+        .into());
+    }
+
+    let mut encoder = png_encoder(w, first, scale)?;
+    encoder.set_animated(frames.len() as u32, 0)?;
+    encoder.set_frame_delay(delay_ms, 1000)?;
+    let mut writer = encoder.write_header()?;
+    for frame in frames {
+        writer.write_image_data(&frame.scaled_rgb(scale))?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkerboard() -> Image {
+        Image::from_dense(&[true, false, false, true], 2, |&white| {
+            if white {
+                WHITE
+            } else {
+                BLACK
+            }
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_ppm() {
+        let mut buf = Vec::new();
+        checkerboard().write_ppm(&mut buf, 2).unwrap();
+        let header = b"P6\n4 4\n255\n";
+        assert!(buf.starts_with(header));
+        let data = &buf[header.len()..];
+        assert_eq!(data.len(), 4 * 4 * 3);
+        // second row of the first cell row, third pixel: a black cell
+        assert_eq!(&data[(4 + 2) * 3..(4 + 3) * 3], &BLACK);
+        assert_eq!(&data[(4 + 1) * 3..(4 + 2) * 3], &WHITE);
+    }
+
+    #[test]
+    fn test_from_canvas() {
+        let canvas: Canvas<bool> = vec![((-1, 5), true), ((0, 6), false)].into_iter().collect();
+        let image = Image::from_canvas(&canvas, |_| WHITE, [1, 2, 3]);
+        assert_eq!((image.width(), image.height()), (2, 2));
+        assert_eq!(image.get(0, 0), Some(WHITE));
+        assert_eq!(image.get(1, 0), Some([1, 2, 3]));
+        assert_eq!(image.get(2, 0), None);
+
+        assert!(Image::from_dense(&[1, 2, 3], 2, |_| BLACK).is_err());
+    }
+
+    #[test]
+    fn test_svg_and_png() {
+        let mut svg = Vec::new();
+        checkerboard().write_svg(&mut svg, 10).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert_eq!(svg.matches("<rect").count(), 4);
+        assert!(svg.contains(r##"<rect x="10" y="0" width="10" height="10" fill="#000000"/>"##));
+
+        let mut png = Vec::new();
+        checkerboard().write_png(&mut png, 3).unwrap();
+        let decoder = png::Decoder::new(&png[..]);
+        let reader = decoder.read_info().unwrap();
+        assert_eq!((reader.info().width, reader.info().height), (6, 6));
+
+        let mut apng = Vec::new();
+        write_animation(&[checkerboard(), checkerboard()], &mut apng, 1, 100).unwrap();
+        let reader = png::Decoder::new(&apng[..]).read_info().unwrap();
+        assert_eq!(reader.info().animation_control().unwrap().num_frames, 2);
+    }
+}
@@ -0,0 +1,177 @@
+//! Recognition of the 6 pixel high block letters the puzzles use to render messages.
+
+use crate::tui::Canvas;
+
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
+
+pub const HEIGHT: usize = 6;
+
+const FONT: [(char, [&str; HEIGHT]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+type Glyph = Vec<Vec<bool>>;
+
+/// Drops the leading and trailing unlit columns of a glyph.
+fn trim(glyph: &[Vec<bool>]) -> Glyph {
+    let width = glyph.iter().map(Vec::len).max().unwrap_or(0);
+    let is_lit = |x: usize| glyph.iter().any(|row| row.get(x) == Some(&true));
+    let start = (0..width).find(|&x| is_lit(x)).unwrap_or(width);
+    let end = (0..width)
+        .rev()
+        .find(|&x| is_lit(x))
+        .map_or(start, |x| x + 1);
+    glyph
+        .iter()
+        .map(|row| (start..end).map(|x| row.get(x) == Some(&true)).collect())
+        .collect()
+}
+
+fn to_art(glyph: &[Vec<bool>]) -> String {
+    glyph
+        .iter()
+        .map(|row| row.iter().map(|&lit| if lit { '#' } else { '.' }).collect())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn lookup(glyph: &[Vec<bool>]) -> Option<char> {
+    FONT.iter().find_map(|(c, rows)| {
+        let font_glyph: Glyph = rows
+            .iter()
+            .map(|row| row.bytes().map(|b| b == b'#').collect())
+            .collect();
+        if trim(&font_glyph) == glyph {
+            Some(*c)
+        } else {
+            None
+        }
+    })
+}
+
+/// Reads the letters of an image given row by row.
+///
+/// Unlit rows and columns around the text are ignored. Letters are separated by at least one
+/// unlit column.
+pub fn recognize(rows: &[Vec<bool>]) -> Result<String> {
+    let rows: Vec<&Vec<bool>> = rows.iter().skip_while(|row| !row.contains(&true)).collect();
+    let height = rows
+        .iter()
+        .rposition(|row| row.contains(&true))
+        .map_or(0, |y| y + 1);
+    if height != HEIGHT {
+        return Err(format!("text must be {} pixels high, but is {}", HEIGHT, height).into());
+    }
+    let rows = &rows[..height];
+
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let is_lit = |x: usize| rows.iter().any(|row| row.get(x) == Some(&true));
+
+    let mut text = String::new();
+    let mut x = 0;
+    while x < width {
+        if !is_lit(x) {
+            x += 1;
+            continue;
+        }
+        let start = x;
+        while x < width && is_lit(x) {
+            x += 1;
+        }
+        let glyph: Glyph = rows
+            .iter()
+            .map(|row| (start..x).map(|x| row.get(x) == Some(&true)).collect())
+            .collect();
+        let c = lookup(&glyph).ok_or_else(|| {
+            format!(
+                "unrecognized glyph #{} at column {}:\n{}",
+                text.len() + 1,
+                start,
+                to_art(&glyph)
+            )
+        })?;
+        text.push(c);
+    }
+
+    Ok(text)
+}
+
+/// Reads the letters of the bounding box of a canvas.
+pub fn recognize_canvas<T>(canvas: &Canvas<T>, is_lit: impl Fn(&T) -> bool) -> Result<String> {
+    let ((x0, y0), (x1, y1)) = canvas.bounds().ok_or("empty canvas")?;
+    let rows: Vec<Vec<bool>> = (y0..=y1)
+        .map(|y| {
+            (x0..=x1)
+                .map(|x| canvas.get((x, y)).is_some_and(&is_lit))
+                .collect()
+        })
+        .collect();
+    recognize(&rows)
+}
+
+/// Reads the letters of a text rendering in which `lit` marks the lit pixels.
+pub fn recognize_str(art: &str, lit: char) -> Result<String> {
+    let rows: Vec<Vec<bool>> = art
+        .lines()
+        .map(|l| l.chars().map(|c| c == lit).collect())
+        .collect();
+    recognize(&rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recognize() {
+        let art = r#"
+ X  X  XXX
+ X  X   X
+ XXXX   X
+ X  X   X
+ X  X   X
+ X  X  XXX
+"#;
+        assert_eq!(recognize_str(art, 'X').unwrap(), "HI");
+
+        let font: String = FONT.iter().map(|(c, _)| *c).collect();
+        let art: String = (0..HEIGHT)
+            .map(|y| {
+                let row: Vec<&str> = FONT.iter().map(|(_, rows)| rows[y]).collect();
+                row.join(".") + "\n"
+            })
+            .collect();
+        assert_eq!(recognize_str(&art, '#').unwrap(), font);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = recognize_str("#\n#\n#", '#').unwrap_err();
+        assert_eq!(err.to_string(), "text must be 6 pixels high, but is 3");
+
+        let art = "#..#..#.\n#..#..#.\n####..#.\n#..#....\n#..#..#.\n#..#..#.";
+        let err = recognize_str(art, '#').unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unrecognized glyph #2 at column 6:\n#\n#\n#\n.\n#\n#"
+        );
+    }
+}
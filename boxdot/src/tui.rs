@@ -0,0 +1,286 @@
+//! Terminal views for the puzzles drawing on a 2D screen.
+//!
+//! A [`Canvas`] stores cells keyed by coordinates, a [`Palette`] turns cells into glyphs and a
+//! [`Terminal`] draws the result below a status bar, redrawing only cells which changed since the
+//! last frame.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io::{Stdout, Write};
+use termion::event::Key;
+use termion::input::{Keys, TermRead};
+use termion::raw::{IntoRawMode, RawTerminal};
+use termion::{clear, cursor, AsyncReader};
+
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
+
+pub type Coord = (i64, i64);
+
+/// Sparse 2D canvas; x grows to the right and y grows downwards.
+#[derive(Debug, Clone)]
+pub struct Canvas<T> {
+    cells: HashMap<Coord, T>,
+}
+
+impl<T> Default for Canvas<T> {
+    fn default() -> Self {
+        Self {
+            cells: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Canvas<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, pos: Coord, cell: T) -> Option<T> {
+        self.cells.insert(pos, cell)
+    }
+
+    pub fn get(&self, pos: Coord) -> Option<&T> {
+        self.cells.get(&pos)
+    }
+
+    pub fn remove(&mut self, pos: Coord) -> Option<T> {
+        self.cells.remove(&pos)
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Coord, &T)> {
+        self.cells.iter().map(|(&pos, cell)| (pos, cell))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.cells.values()
+    }
+
+    /// Returns the inclusive (min, max) corners of all cells.
+    pub fn bounds(&self) -> Option<(Coord, Coord)> {
+        if self.cells.is_empty() {
+            return None;
+        }
+        let bounds = self.cells.keys().fold(
+            ((i64::MAX, i64::MAX), (i64::MIN, i64::MIN)),
+            |(min, max), pos| {
+                let min = (min.0.min(pos.0), min.1.min(pos.1));
+                let max = (max.0.max(pos.0), max.1.max(pos.1));
+                (min, max)
+            },
+        );
+        Some(bounds)
+    }
+
+    /// Renders the bounding box of the canvas into lines of glyphs.
+    pub fn lines(&self, palette: &impl Palette<T>) -> Vec<String> {
+        match self.bounds() {
+            Some(bounds) => self.lines_in(bounds, palette),
+            None => Vec::new(),
+        }
+    }
+
+    /// Renders the inclusive rectangle between the given corners into lines of glyphs.
+    pub fn lines_in(
+        &self,
+        ((x0, y0), (x1, y1)): (Coord, Coord),
+        palette: &impl Palette<T>,
+    ) -> Vec<String> {
+        (y0..=y1)
+            .map(|y| {
+                (x0..=x1)
+                    .map(|x| match self.get((x, y)) {
+                        Some(cell) => palette.glyph(cell),
+                        None => palette.background(),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn render(&self, palette: &impl Palette<T>) -> String {
+        self.lines(palette).join("\n")
+    }
+}
+
+impl<T> std::iter::FromIterator<(Coord, T)> for Canvas<T> {
+    fn from_iter<I: IntoIterator<Item = (Coord, T)>>(iter: I) -> Self {
+        Self {
+            cells: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Maps cells to glyphs.
+pub trait Palette<T> {
+    fn glyph(&self, cell: &T) -> char;
+
+    /// Glyph of coordinates without a cell.
+    fn background(&self) -> char {
+        ' '
+    }
+}
+
+impl<T, F: Fn(&T) -> char> Palette<T> for F {
+    fn glyph(&self, cell: &T) -> char {
+        self(cell)
+    }
+}
+
+impl<T: Hash + Eq> Palette<T> for HashMap<T, char> {
+    fn glyph(&self, cell: &T) -> char {
+        self.get(cell).copied().unwrap_or('?')
+    }
+}
+
+/// Terminal drawing a status bar in the first line and a canvas below it.
+pub struct Terminal<W: Write> {
+    out: W,
+    status: Option<String>,
+    origin: Option<Coord>,
+    screen: Vec<Vec<char>>,
+    keys: Option<Keys<AsyncReader>>,
+}
+
+impl Terminal<RawTerminal<Stdout>> {
+    /// Switches stdout into raw mode and starts listening for key presses.
+    pub fn stdout() -> Result<Self> {
+        let out = std::io::stdout().into_raw_mode()?;
+        let mut terminal = Self::new(out)?;
+        terminal.keys = Some(termion::async_stdin().keys());
+        Ok(terminal)
+    }
+}
+
+impl<W: Write> Terminal<W> {
+    pub fn new(mut out: W) -> Result<Self> {
+        write!(out, "{}{}", clear::All, cursor::Hide)?;
+        Ok(Self {
+            out,
+            status: None,
+            origin: None,
+            screen: Vec::new(),
+            keys: None,
+        })
+    }
+
+    /// Draws the status bar and the canvas, touching only the cells which changed.
+    ///
+    /// The top left corner of the view only moves when the canvas grows to the left or upwards,
+    /// so that cells keep their place on the screen between frames.
+    pub fn draw<T>(
+        &mut self,
+        status: &str,
+        canvas: &Canvas<T>,
+        palette: &impl Palette<T>,
+    ) -> Result<()> {
+        if self.status.as_deref() != Some(status) {
+            write!(
+                self.out,
+                "{}{}{}",
+                cursor::Goto(1, 1),
+                status,
+                clear::UntilNewline
+            )?;
+            self.status = Some(status.to_string());
+        }
+
+        let screen: Vec<Vec<char>> = match canvas.bounds() {
+            Some((min, max)) => {
+                let origin = match self.origin {
+                    Some(origin) => (origin.0.min(min.0), origin.1.min(min.1)),
+                    None => min,
+                };
+                self.origin = Some(origin);
+                canvas
+                    .lines_in((origin, max), palette)
+                    .into_iter()
+                    .map(|l| l.chars().collect())
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        let height = screen.len().max(self.screen.len());
+        for y in 0..height {
+            let line = screen.get(y).map(Vec::as_slice).unwrap_or(&[]);
+            let prev = self.screen.get(y).map(Vec::as_slice).unwrap_or(&[]);
+            let width = line.len().max(prev.len());
+            for x in 0..width {
+                let c = line.get(x).copied().unwrap_or(' ');
+                if prev.get(x).copied().unwrap_or(' ') != c {
+                    write!(
+                        self.out,
+                        "{}{}",
+                        cursor::Goto(x as u16 + 1, y as u16 + 2),
+                        c
+                    )?;
+                }
+            }
+        }
+        self.screen = screen;
+
+        self.out.flush()?;
+        Ok(())
+    }
+
+    /// Returns the next pressed key without blocking.
+    pub fn poll_key(&mut self) -> Option<Key> {
+        self.keys.as_mut()?.next()?.ok()
+    }
+}
+
+impl<W: Write> Drop for Terminal<W> {
+    fn drop(&mut self) {
+        let height = self.screen.len() as u16 + 2;
+        let _ = write!(self.out, "{}{}", cursor::Goto(1, height), cursor::Show);
+        let _ = self.out.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let canvas: Canvas<u8> = vec![((-1, 0), 1), ((1, 1), 2)].into_iter().collect();
+        assert_eq!(canvas.bounds(), Some(((-1, 0), (1, 1))));
+        let palette: HashMap<u8, char> = vec![(1, '#'), (2, 'o')].into_iter().collect();
+        assert_eq!(canvas.render(&palette), "#  \n  o");
+        assert_eq!(canvas.render(&|&c: &u8| (b'0' + c) as char), "1  \n  2");
+    }
+
+    #[test]
+    fn test_draw_diff() {
+        let palette = |_: &bool| '#';
+        let mut canvas = Canvas::new();
+        canvas.set((0, 0), true);
+        canvas.set((2, 0), true);
+
+        let mut terminal = Terminal::new(Vec::new()).unwrap();
+        terminal.draw("score", &canvas, &palette).unwrap();
+        terminal.out.clear();
+
+        // nothing changed
+        terminal.draw("score", &canvas, &palette).unwrap();
+        assert!(terminal.out.is_empty());
+
+        canvas.remove((0, 0));
+        canvas.set((1, 0), true);
+        terminal.draw("score", &canvas, &palette).unwrap();
+        let expected = format!("{} {}#", cursor::Goto(1, 2), cursor::Goto(2, 2));
+        assert_eq!(String::from_utf8(terminal.out.clone()).unwrap(), expected);
+    }
+}